@@ -0,0 +1,96 @@
+// src/elf/error.rs
+//! Errores al validar y cargar un ELF
+
+use core::fmt;
+use crate::syscall::Errno;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfError {
+    /// El archivo se acaba antes que una estructura que dice contener
+    Truncated,
+    /// No empieza por `\x7fELF`
+    BadMagic,
+    /// `EI_CLASS` no es la que espera el cargador
+    WrongClass(u8),
+    /// `EI_DATA`: solo hay soporte para little-endian
+    WrongEndianness(u8),
+    WrongVersion,
+    WrongMachine(u16),
+    /// `e_type` no es ET_EXEC ni ET_DYN
+    NotExecutable(u16),
+    /// `e_phentsize` no coincide con el tamaño de un program header
+    BadPhdrSize(u16),
+    /// `e_shentsize` no coincide con el tamaño de una sección
+    BadShdrSize(u16),
+    NoSegments,
+    /// Segmento con más datos en el archivo que en memoria o con una
+    /// alineación que no es potencia de dos
+    BadSegment { vaddr: u64 },
+    WriteAndExecute { vaddr: u64 },
+    OverlappingSegments { first: u64, second: u64 },
+    /// Segmento que llega a la mitad del kernel (o se sale de las
+    /// direcciones canónicas, o de los 4 GiB de un programa de 32 bits)
+    KernelHalfAddress(u64),
+    InterpreterNotFound,
+    BadInterpreter(&'static str),
+    BadDynamic(&'static str),
+    UnsupportedRelocation(u32),
+    BadTls,
+    /// Programa de 32 bits que necesita algo que el modo compatibilidad
+    /// todavía no ofrece
+    UnsupportedCompat(&'static str),
+    /// argv y envp no caben en la pila inicial
+    ArgumentsTooLarge,
+    /// Error al reservar o mapear memoria del espacio de direcciones
+    Memory(&'static str),
+}
+
+impl ElfError {
+    /// Error de `execve` que corresponde
+    pub fn errno(self) -> Errno {
+        match self {
+            ElfError::InterpreterNotFound => Errno::ENOENT,
+            ElfError::ArgumentsTooLarge => Errno::E2BIG,
+            ElfError::Memory(_) => Errno::ENOMEM,
+            _ => Errno::ENOEXEC,
+        }
+    }
+}
+
+// Los errores de `AddressSpace` siguen siendo cadenas
+impl From<&'static str> for ElfError {
+    fn from(msg: &'static str) -> Self {
+        ElfError::Memory(msg)
+    }
+}
+
+impl fmt::Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ElfError::Truncated => write!(f, "Archivo truncado"),
+            ElfError::BadMagic => write!(f, "No es un archivo ELF válido"),
+            ElfError::WrongClass(class) => write!(f, "Clase de ELF no soportada ({})", class),
+            ElfError::WrongEndianness(data) => write!(f, "Orden de bytes no soportado ({})", data),
+            ElfError::WrongVersion => write!(f, "Versión de ELF no soportada"),
+            ElfError::WrongMachine(machine) => write!(f, "ELF para otra arquitectura (e_machine {})", machine),
+            ElfError::NotExecutable(kind) => write!(f, "El ELF no es un ejecutable (e_type {})", kind),
+            ElfError::BadPhdrSize(size) => write!(f, "Tamaño de program header no válido ({} bytes)", size),
+            ElfError::BadShdrSize(size) => write!(f, "Tamaño de sección no válido ({} bytes)", size),
+            ElfError::NoSegments => write!(f, "ELF sin segmentos cargables"),
+            ElfError::BadSegment { vaddr } => write!(f, "Segmento no válido en 0x{:x}", vaddr),
+            ElfError::WriteAndExecute { vaddr } => write!(f, "Segmento escribible y ejecutable (W^X) en 0x{:x}", vaddr),
+            ElfError::OverlappingSegments { first, second } => {
+                write!(f, "Segmentos solapados en 0x{:x} y 0x{:x}", first, second)
+            }
+            ElfError::KernelHalfAddress(vaddr) => write!(f, "Segmento fuera del espacio de usuario (0x{:x})", vaddr),
+            ElfError::InterpreterNotFound => write!(f, "Intérprete no encontrado"),
+            ElfError::BadInterpreter(why) => write!(f, "Intérprete no válido: {}", why),
+            ElfError::BadDynamic(why) => write!(f, "PT_DYNAMIC no válido: {}", why),
+            ElfError::UnsupportedRelocation(kind) => write!(f, "Tipo de relocación no soportado ({})", kind),
+            ElfError::BadTls => write!(f, "PT_TLS no válido"),
+            ElfError::UnsupportedCompat(what) => write!(f, "Programa de 32 bits no soportado: {}", what),
+            ElfError::ArgumentsTooLarge => write!(f, "Argumentos demasiado grandes para la pila"),
+            ElfError::Memory(why) => write!(f, "{}", why),
+        }
+    }
+}
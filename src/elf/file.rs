@@ -0,0 +1,410 @@
+// src/elf/file.rs
+//! Lectura de archivos ELF de cualquier clase
+//!
+//! `ElfFile` comprueba la identificación y la cabecera y da acceso a los
+//! program headers, las secciones, las tablas de cadenas y de símbolos y las
+//! notas. Las estructuras de 32 y 64 bits (`header32`, `header64`) se leen
+//! del archivo sin exigir alineación y se convierten a los tipos de aquí,
+//! con todos los campos de direcciones y tamaños en `u64`.
+
+use core::mem::size_of;
+use super::error::ElfError;
+use super::header32::{Elf32_Ehdr, Elf32_Phdr, Elf32_Shdr, Elf32_Sym};
+use super::header64::{Elf64_Ehdr, Elf64_Phdr, Elf64_Shdr, Elf64_Sym};
+use super::types::{EI_CLASS, EI_DATA, EI_VERSION, ELFCLASS32, ELFCLASS64, ELFDATA2LSB, EV_CURRENT};
+use super::types::{ET_DYN, ET_EXEC, EM_386, EM_X86_64};
+use super::types::{PT_NULL, PT_LOAD, PT_DYNAMIC, PT_INTERP, PT_NOTE, PT_SHLIB, PT_PHDR, PT_TLS, PF_R, PF_W, PF_X};
+use super::types::{SHT_NULL, SHT_PROGBITS, SHT_SYMTAB, SHT_STRTAB, SHT_NOTE, SHT_NOBITS, SHT_DYNSYM};
+use super::types::{SHN_UNDEF, STT_FUNC, STT_OBJECT};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    Elf32,
+    Elf64,
+}
+
+/// Cabecera del archivo
+#[derive(Debug, Clone, Copy)]
+pub struct Header {
+    pub e_ident: [u8; 16],
+    pub e_type: u16,
+    pub e_machine: u16,
+    pub e_version: u32,
+    pub e_entry: u64,
+    pub e_phoff: u64,
+    pub e_shoff: u64,
+    pub e_flags: u32,
+    pub e_phentsize: u16,
+    pub e_phnum: u16,
+    pub e_shentsize: u16,
+    pub e_shnum: u16,
+    pub e_shstrndx: u16,
+}
+
+impl Header {
+    /// Nombre del tipo de archivo, como lo escribe `readelf`
+    pub fn type_name(&self) -> &'static str {
+        match self.e_type {
+            ET_EXEC => "EXEC",
+            ET_DYN => "DYN",
+            _ => "?",
+        }
+    }
+
+    pub fn machine_name(&self) -> &'static str {
+        match self.e_machine {
+            EM_X86_64 => "x86-64",
+            EM_386 => "i386",
+            _ => "?",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ProgramHeader {
+    pub p_type: u32,
+    pub p_flags: u32,
+    pub p_offset: u64,
+    pub p_vaddr: u64,
+    pub p_filesz: u64,
+    pub p_memsz: u64,
+    pub p_align: u64,
+}
+
+impl ProgramHeader {
+    pub fn type_name(&self) -> &'static str {
+        match self.p_type {
+            PT_NULL => "NULL",
+            PT_LOAD => "LOAD",
+            PT_DYNAMIC => "DYNAMIC",
+            PT_INTERP => "INTERP",
+            PT_NOTE => "NOTE",
+            PT_SHLIB => "SHLIB",
+            PT_PHDR => "PHDR",
+            PT_TLS => "TLS",
+            _ => "?",
+        }
+    }
+
+    /// Permisos como `RWX`, con `-` los que faltan
+    pub fn permissions(&self) -> [char; 3] {
+        let flag = |bit, c| if self.p_flags & bit != 0 { c } else { '-' };
+        [flag(PF_R, 'R'), flag(PF_W, 'W'), flag(PF_X, 'X')]
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SectionHeader {
+    pub sh_name: u32,
+    pub sh_type: u32,
+    pub sh_flags: u64,
+    pub sh_addr: u64,
+    pub sh_offset: u64,
+    pub sh_size: u64,
+    pub sh_link: u32,
+    pub sh_info: u32,
+    pub sh_addralign: u64,
+    pub sh_entsize: u64,
+}
+
+impl SectionHeader {
+    pub fn type_name(&self) -> &'static str {
+        match self.sh_type {
+            SHT_NULL => "NULL",
+            SHT_PROGBITS => "PROGBITS",
+            SHT_SYMTAB => "SYMTAB",
+            SHT_STRTAB => "STRTAB",
+            SHT_NOTE => "NOTE",
+            SHT_NOBITS => "NOBITS",
+            SHT_DYNSYM => "DYNSYM",
+            _ => "?",
+        }
+    }
+}
+
+/// Entrada de una tabla de símbolos tal como está en el archivo
+#[derive(Debug, Clone, Copy)]
+pub struct RawSymbol {
+    pub st_name: u32,
+    pub st_info: u8,
+    pub st_shndx: u16,
+    pub st_value: u64,
+    pub st_size: u64,
+}
+
+/// Símbolo con su nombre ya resuelto
+#[derive(Debug, Clone, Copy)]
+pub struct Symbol<'a> {
+    pub name: &'a str,
+    pub value: u64,
+    pub size: u64,
+    pub info: u8,
+    pub shndx: u16,
+}
+
+impl Symbol<'_> {
+    /// STT_*: función, objeto, sección...
+    pub fn kind(&self) -> u8 {
+        self.info & 0xf
+    }
+
+    pub fn is_defined(&self) -> bool {
+        self.shndx != SHN_UNDEF
+    }
+
+    /// Función u objeto definido aquí: lo único que puede contener una dirección
+    pub fn is_addressable(&self) -> bool {
+        self.is_defined() && matches!(self.kind(), STT_FUNC | STT_OBJECT)
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self.kind() {
+            STT_FUNC => "FUNC",
+            STT_OBJECT => "OBJECT",
+            _ => "?",
+        }
+    }
+}
+
+/// Nota de PT_NOTE o de una sección SHT_NOTE
+#[derive(Debug, Clone, Copy)]
+pub struct Note<'a> {
+    /// Propietario, sin el NUL final (`GNU`, `Linux`...)
+    pub name: &'a [u8],
+    pub n_type: u32,
+    pub desc: &'a [u8],
+}
+
+/// Lee un `T` en `offset` sin exigir alineación, si cabe en `data`
+fn read<T: Copy>(data: &[u8], offset: u64) -> Option<T> {
+    let end = offset.checked_add(size_of::<T>() as u64)?;
+    if end > data.len() as u64 {
+        return None;
+    }
+    Some(unsafe { core::ptr::read_unaligned(data.as_ptr().add(offset as usize) as *const T) })
+}
+
+/// Trozo `[offset, offset + size)` de `data`, si está entero
+fn slice(data: &[u8], offset: u64, size: u64) -> Option<&[u8]> {
+    let end = offset.checked_add(size)?;
+    if end > data.len() as u64 {
+        return None;
+    }
+    Some(&data[offset as usize..end as usize])
+}
+
+/// Archivo ELF validado
+#[derive(Clone, Copy)]
+pub struct ElfFile<'a> {
+    data: &'a [u8],
+    class: Class,
+    header: Header,
+}
+
+impl<'a> ElfFile<'a> {
+    /// Comprueba la identificación (magic, clase, orden de bytes, versión) y
+    /// que las tablas de program headers y secciones estén en el archivo
+    pub fn parse(data: &'a [u8]) -> Result<Self, ElfError> {
+        let ident: [u8; 16] = read(data, 0).ok_or(ElfError::Truncated)?;
+        if ident[0..4] != [0x7F, b'E', b'L', b'F'] {
+            return Err(ElfError::BadMagic);
+        }
+        let class = match ident[EI_CLASS] {
+            ELFCLASS32 => Class::Elf32,
+            ELFCLASS64 => Class::Elf64,
+            other => return Err(ElfError::WrongClass(other)),
+        };
+        if ident[EI_DATA] != ELFDATA2LSB {
+            return Err(ElfError::WrongEndianness(ident[EI_DATA]));
+        }
+        if ident[EI_VERSION] != EV_CURRENT {
+            return Err(ElfError::WrongVersion);
+        }
+
+        let (header, phdr_size, shdr_size): (Header, _, _) = match class {
+            Class::Elf32 => (
+                read::<Elf32_Ehdr>(data, 0).ok_or(ElfError::Truncated)?.into(),
+                size_of::<Elf32_Phdr>(),
+                size_of::<Elf32_Shdr>(),
+            ),
+            Class::Elf64 => (
+                read::<Elf64_Ehdr>(data, 0).ok_or(ElfError::Truncated)?.into(),
+                size_of::<Elf64_Phdr>(),
+                size_of::<Elf64_Shdr>(),
+            ),
+        };
+        if header.e_version != EV_CURRENT as u32 {
+            return Err(ElfError::WrongVersion);
+        }
+
+        if header.e_phnum > 0 {
+            if header.e_phentsize as usize != phdr_size {
+                return Err(ElfError::BadPhdrSize(header.e_phentsize));
+            }
+            let size = header.e_phnum as u64 * phdr_size as u64;
+            slice(data, header.e_phoff, size).ok_or(ElfError::Truncated)?;
+        }
+        if header.e_shnum > 0 {
+            if header.e_shentsize as usize != shdr_size {
+                return Err(ElfError::BadShdrSize(header.e_shentsize));
+            }
+            let size = header.e_shnum as u64 * shdr_size as u64;
+            slice(data, header.e_shoff, size).ok_or(ElfError::Truncated)?;
+        }
+
+        Ok(ElfFile { data, class, header })
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn class(&self) -> Class {
+        self.class
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Lee un `T` en `offset` del archivo (para estructuras propias de una
+    /// arquitectura, como las relocaciones)
+    pub fn read<T: Copy>(&self, offset: u64) -> Option<T> {
+        read(self.data, offset)
+    }
+
+    pub fn program_header(&self, index: u16) -> Option<ProgramHeader> {
+        if index >= self.header.e_phnum {
+            return None;
+        }
+        let offset = self.header.e_phoff + index as u64 * self.header.e_phentsize as u64;
+        match self.class {
+            Class::Elf32 => read::<Elf32_Phdr>(self.data, offset).map(Into::into),
+            Class::Elf64 => read::<Elf64_Phdr>(self.data, offset).map(Into::into),
+        }
+    }
+
+    pub fn program_headers(&self) -> impl Iterator<Item = ProgramHeader> + '_ {
+        (0..self.header.e_phnum).filter_map(|i| self.program_header(i))
+    }
+
+    /// Datos del segmento en el archivo, si están enteros
+    pub fn segment_data(&self, phdr: &ProgramHeader) -> Result<&'a [u8], ElfError> {
+        slice(self.data, phdr.p_offset, phdr.p_filesz).ok_or(ElfError::Truncated)
+    }
+
+    pub fn section_header(&self, index: u16) -> Option<SectionHeader> {
+        if index >= self.header.e_shnum {
+            return None;
+        }
+        let offset = self.header.e_shoff + index as u64 * self.header.e_shentsize as u64;
+        match self.class {
+            Class::Elf32 => read::<Elf32_Shdr>(self.data, offset).map(Into::into),
+            Class::Elf64 => read::<Elf64_Shdr>(self.data, offset).map(Into::into),
+        }
+    }
+
+    pub fn section_headers(&self) -> impl Iterator<Item = SectionHeader> + '_ {
+        (0..self.header.e_shnum).filter_map(|i| self.section_header(i))
+    }
+
+    /// Contenido de la sección en el archivo (vacío para SHT_NOBITS)
+    pub fn section_data(&self, section: &SectionHeader) -> Result<&'a [u8], ElfError> {
+        if section.sh_type == SHT_NOBITS {
+            return Ok(&[]);
+        }
+        slice(self.data, section.sh_offset, section.sh_size).ok_or(ElfError::Truncated)
+    }
+
+    /// Cadena terminada en NUL en `offset` de la tabla de cadenas `strtab`
+    pub fn string(&self, strtab: &SectionHeader, offset: u32) -> Option<&'a str> {
+        let table = self.section_data(strtab).ok()?;
+        let bytes = table.get(offset as usize..)?;
+        let len = bytes.iter().position(|&b| b == 0)?;
+        core::str::from_utf8(&bytes[..len]).ok()
+    }
+
+    /// Nombre de la sección (de la tabla `e_shstrndx`)
+    pub fn section_name(&self, section: &SectionHeader) -> Option<&'a str> {
+        let strtab = self.section_header(self.header.e_shstrndx)?;
+        self.string(&strtab, section.sh_name)
+    }
+
+    /// Símbolos de la tabla completa (SHT_SYMTAB) o, si el ejecutable está
+    /// despojado, de la dinámica (SHT_DYNSYM)
+    pub fn symbols(&self) -> impl Iterator<Item = Symbol<'a>> + '_ {
+        let table = self.section_headers()
+            .find(|section| section.sh_type == SHT_SYMTAB)
+            .or_else(|| self.section_headers().find(|section| section.sh_type == SHT_DYNSYM));
+        let strtab = table.and_then(|table| self.section_header(table.sh_link as u16));
+        let entry_size = match self.class {
+            Class::Elf32 => size_of::<Elf32_Sym>(),
+            Class::Elf64 => size_of::<Elf64_Sym>(),
+        } as u64;
+        let count = table.map_or(0, |table| table.sh_size / entry_size);
+
+        (0..count).filter_map(move |i| {
+            let offset = table?.sh_offset.checked_add(i * entry_size)?;
+            let raw: RawSymbol = match self.class {
+                Class::Elf32 => read::<Elf32_Sym>(self.data, offset)?.into(),
+                Class::Elf64 => read::<Elf64_Sym>(self.data, offset)?.into(),
+            };
+            let name = strtab.and_then(|strtab| self.string(&strtab, raw.st_name)).unwrap_or("");
+            Some(Symbol {
+                name,
+                value: raw.st_value,
+                size: raw.st_size,
+                info: raw.st_info,
+                shndx: raw.st_shndx,
+            })
+        })
+    }
+
+    /// Función u objeto que contiene `addr` y la distancia desde su inicio
+    pub fn symbolize(&self, addr: u64) -> Option<(Symbol<'a>, u64)> {
+        self.symbols()
+            .filter(Symbol::is_addressable)
+            .find(|sym| sym.value <= addr && addr - sym.value < sym.size.max(1))
+            .map(|sym| (sym, addr - sym.value))
+    }
+
+    /// Notas de los segmentos PT_NOTE o, si no hay ninguno, de las secciones
+    /// SHT_NOTE
+    pub fn notes(&self) -> impl Iterator<Item = Note<'a>> + '_ {
+        let from_segments = self.program_headers().any(|phdr| phdr.p_type == PT_NOTE);
+        let segments = self.program_headers()
+            .filter(move |phdr| from_segments && phdr.p_type == PT_NOTE)
+            .filter_map(|phdr| Some((self.segment_data(&phdr).ok()?, phdr.p_align)));
+        let sections = self.section_headers()
+            .filter(move |section| !from_segments && section.sh_type == SHT_NOTE)
+            .filter_map(|section| Some((self.section_data(&section).ok()?, section.sh_addralign)));
+        segments.chain(sections).flat_map(|(data, align)| NoteIter { data, align: align.max(4) })
+    }
+}
+
+/// Recorre las notas de un bloque: `namesz`, `descsz`, `type`, y después el
+/// nombre y la descripción, cada uno empezando en un múltiplo de `align`
+/// (que no tiene por qué ser potencia de dos: sale del archivo)
+struct NoteIter<'a> {
+    data: &'a [u8],
+    align: u64,
+}
+
+impl<'a> Iterator for NoteIter<'a> {
+    type Item = Note<'a>;
+
+    fn next(&mut self) -> Option<Note<'a>> {
+        let align = |offset: u64| offset.checked_next_multiple_of(self.align);
+        let namesz = read::<u32>(self.data, 0)? as u64;
+        let descsz = read::<u32>(self.data, 4)? as u64;
+        let n_type = read::<u32>(self.data, 8)?;
+        let name = slice(self.data, 12, namesz)?;
+        let desc_offset = align(12 + namesz)?;
+        let desc = slice(self.data, desc_offset, descsz)?;
+
+        let next = align(desc_offset + descsz)?.min(self.data.len() as u64);
+        self.data = &self.data[next as usize..];
+        let name = name.strip_suffix(&[0]).unwrap_or(name);
+        Some(Note { name, n_type, desc })
+    }
+}
@@ -0,0 +1,125 @@
+// src/elf/header32.rs
+//! Estructuras de ELFCLASS32 y su conversión a los tipos genéricos de `file`
+
+use super::file::{Header, ProgramHeader, SectionHeader, RawSymbol};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Elf32_Ehdr {
+    pub e_ident: [u8; 16],
+    pub e_type: u16,
+    pub e_machine: u16,
+    pub e_version: u32,
+    pub e_entry: u32,
+    pub e_phoff: u32,
+    pub e_shoff: u32,
+    pub e_flags: u32,
+    pub e_ehsize: u16,
+    pub e_phentsize: u16,
+    pub e_phnum: u16,
+    pub e_shentsize: u16,
+    pub e_shnum: u16,
+    pub e_shstrndx: u16,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Elf32_Phdr {
+    pub p_type: u32,
+    pub p_offset: u32,
+    pub p_vaddr: u32,
+    pub p_paddr: u32,
+    pub p_filesz: u32,
+    pub p_memsz: u32,
+    pub p_flags: u32,
+    pub p_align: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Elf32_Shdr {
+    pub sh_name: u32,
+    pub sh_type: u32,
+    pub sh_flags: u32,
+    pub sh_addr: u32,
+    pub sh_offset: u32,
+    pub sh_size: u32,
+    pub sh_link: u32,
+    pub sh_info: u32,
+    pub sh_addralign: u32,
+    pub sh_entsize: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Elf32_Sym {
+    pub st_name: u32,
+    pub st_value: u32,
+    pub st_size: u32,
+    pub st_info: u8,
+    pub st_other: u8,
+    pub st_shndx: u16,
+}
+
+impl From<Elf32_Ehdr> for Header {
+    fn from(h: Elf32_Ehdr) -> Self {
+        Header {
+            e_ident: h.e_ident,
+            e_type: h.e_type,
+            e_machine: h.e_machine,
+            e_version: h.e_version,
+            e_entry: h.e_entry as u64,
+            e_phoff: h.e_phoff as u64,
+            e_shoff: h.e_shoff as u64,
+            e_flags: h.e_flags,
+            e_phentsize: h.e_phentsize,
+            e_phnum: h.e_phnum,
+            e_shentsize: h.e_shentsize,
+            e_shnum: h.e_shnum,
+            e_shstrndx: h.e_shstrndx,
+        }
+    }
+}
+
+impl From<Elf32_Phdr> for ProgramHeader {
+    fn from(p: Elf32_Phdr) -> Self {
+        ProgramHeader {
+            p_type: p.p_type,
+            p_flags: p.p_flags,
+            p_offset: p.p_offset as u64,
+            p_vaddr: p.p_vaddr as u64,
+            p_filesz: p.p_filesz as u64,
+            p_memsz: p.p_memsz as u64,
+            p_align: p.p_align as u64,
+        }
+    }
+}
+
+impl From<Elf32_Shdr> for SectionHeader {
+    fn from(s: Elf32_Shdr) -> Self {
+        SectionHeader {
+            sh_name: s.sh_name,
+            sh_type: s.sh_type,
+            sh_flags: s.sh_flags as u64,
+            sh_addr: s.sh_addr as u64,
+            sh_offset: s.sh_offset as u64,
+            sh_size: s.sh_size as u64,
+            sh_link: s.sh_link,
+            sh_info: s.sh_info,
+            sh_addralign: s.sh_addralign as u64,
+            sh_entsize: s.sh_entsize as u64,
+        }
+    }
+}
+
+impl From<Elf32_Sym> for RawSymbol {
+    fn from(s: Elf32_Sym) -> Self {
+        RawSymbol {
+            st_name: s.st_name,
+            st_info: s.st_info,
+            st_shndx: s.st_shndx,
+            st_value: s.st_value as u64,
+            st_size: s.st_size as u64,
+        }
+    }
+}
@@ -1,5 +1,7 @@
 // src/elf/header64.rs
-use core::mem;
+//! Estructuras de ELFCLASS64 y su conversión a los tipos genéricos de `file`
+
+use super::file::{Header, ProgramHeader, SectionHeader, RawSymbol};
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -33,18 +35,114 @@ pub struct Elf64_Phdr {
     pub p_align: u64,
 }
 
-impl Elf64_Ehdr {
-    pub fn from_bytes(bytes: &[u8]) -> Option<&Self> {
-        if bytes.len() < mem::size_of::<Self>() {
-            return None;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Elf64_Shdr {
+    pub sh_name: u32,
+    pub sh_type: u32,
+    pub sh_flags: u64,
+    pub sh_addr: u64,
+    pub sh_offset: u64,
+    pub sh_size: u64,
+    pub sh_link: u32,
+    pub sh_info: u32,
+    pub sh_addralign: u64,
+    pub sh_entsize: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Elf64_Sym {
+    pub st_name: u32,
+    pub st_info: u8,
+    pub st_other: u8,
+    pub st_shndx: u16,
+    pub st_value: u64,
+    pub st_size: u64,
+}
+
+/// Entrada de PT_DYNAMIC
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Elf64_Dyn {
+    pub d_tag: i64,
+    pub d_val: u64,
+}
+
+/// Relocación con sumando explícito (las únicas que usa x86_64)
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Elf64_Rela {
+    pub r_offset: u64,
+    pub r_info: u64,
+    pub r_addend: i64,
+}
+
+impl Elf64_Rela {
+    pub fn r_type(&self) -> u32 {
+        self.r_info as u32
+    }
+}
+
+impl From<Elf64_Ehdr> for Header {
+    fn from(h: Elf64_Ehdr) -> Self {
+        Header {
+            e_ident: h.e_ident,
+            e_type: h.e_type,
+            e_machine: h.e_machine,
+            e_version: h.e_version,
+            e_entry: h.e_entry,
+            e_phoff: h.e_phoff,
+            e_shoff: h.e_shoff,
+            e_flags: h.e_flags,
+            e_phentsize: h.e_phentsize,
+            e_phnum: h.e_phnum,
+            e_shentsize: h.e_shentsize,
+            e_shnum: h.e_shnum,
+            e_shstrndx: h.e_shstrndx,
+        }
+    }
+}
+
+impl From<Elf64_Phdr> for ProgramHeader {
+    fn from(p: Elf64_Phdr) -> Self {
+        ProgramHeader {
+            p_type: p.p_type,
+            p_flags: p.p_flags,
+            p_offset: p.p_offset,
+            p_vaddr: p.p_vaddr,
+            p_filesz: p.p_filesz,
+            p_memsz: p.p_memsz,
+            p_align: p.p_align,
         }
-        Some(unsafe { &*(bytes.as_ptr() as *const Self) })
     }
-    
-    pub fn is_valid(&self) -> bool {
-        self.e_ident[0] == 0x7F &&
-        self.e_ident[1] == b'E' &&
-        self.e_ident[2] == b'L' &&
-        self.e_ident[3] == b'F'
+}
+
+impl From<Elf64_Shdr> for SectionHeader {
+    fn from(s: Elf64_Shdr) -> Self {
+        SectionHeader {
+            sh_name: s.sh_name,
+            sh_type: s.sh_type,
+            sh_flags: s.sh_flags,
+            sh_addr: s.sh_addr,
+            sh_offset: s.sh_offset,
+            sh_size: s.sh_size,
+            sh_link: s.sh_link,
+            sh_info: s.sh_info,
+            sh_addralign: s.sh_addralign,
+            sh_entsize: s.sh_entsize,
+        }
+    }
+}
+
+impl From<Elf64_Sym> for RawSymbol {
+    fn from(s: Elf64_Sym) -> Self {
+        RawSymbol {
+            st_name: s.st_name,
+            st_info: s.st_info,
+            st_shndx: s.st_shndx,
+            st_value: s.st_value,
+            st_size: s.st_size,
+        }
     }
 }
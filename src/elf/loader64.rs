@@ -1,114 +1,524 @@
 // src/elf/loader64.rs
-use super::header64::{Elf64_Ehdr, Elf64_Phdr};
-use super::types::PT_LOAD;
-use crate::syscall::Syscalls;
-use crate::memory::map_range;
+use super::error::ElfError;
+use super::file::{Class, ElfFile, Header, ProgramHeader};
+use super::header64::{Elf64_Dyn, Elf64_Rela};
+use super::types::{EM_386, EM_X86_64, ET_EXEC, ET_DYN, PT_LOAD, PT_DYNAMIC, PT_INTERP, PT_PHDR, PT_TLS, PF_X, PF_W, PF_R};
+use super::types::{DT_NULL, DT_RELA, DT_RELASZ, DT_RELAENT, DT_REL, R_X86_64_NONE, R_X86_64_RELATIVE};
+use super::types::{AT_PHDR, AT_PHENT, AT_PHNUM, AT_PAGESZ, AT_BASE, AT_ENTRY};
+use crate::memory::{AddressSpace, RegionKind};
+use crate::usermode::{self, COMPAT_SPACE_END, COMPAT_STACK_TOP, USER_SPACE_END, USER_STACK_TOP};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem::size_of;
 use x86_64::{VirtAddr, structures::paging::PageTableFlags};
 
+/// Dónde se cargan los ejecutables independientes de posición (ET_DYN); la
+/// misma dirección que usa Linux sin ASLR
+const PIE_LOAD_BASE: u64 = 0x0000_5555_5555_4000;
+
+/// Dónde se carga el intérprete de un programa dinámico (AT_BASE), lejos del
+/// programa y por debajo de los mapeos de `mmap`
+const INTERP_LOAD_BASE: u64 = 0x0000_7f00_0000_0000;
+
+/// Bytes reservados para el TCB tras el bloque TLS: el puntero a sí mismo y
+/// el canario de la pila que los compiladores leen en `%fs:0x28`
+const TCB_SIZE: u64 = 64;
+
 pub struct ElfLoader;
 
+/// Programa cargado en su espacio de direcciones, listo para ejecutarse
+pub struct LoadedProgram {
+    pub space: AddressSpace,
+    pub entry: u64,
+    pub stack_top: u64,
+    /// Puntero de hilo inicial (base de FS), si el programa tiene PT_TLS
+    pub thread_pointer: Option<u64>,
+    /// Programa i386 (ELFCLASS32) que se ejecuta en modo compatibilidad
+    pub compat: bool,
+}
+
 impl ElfLoader {
-    pub fn load_and_execute(file: &[u8], syscalls: &mut dyn Syscalls) -> Result<(), &'static str> {
-        crate::println!("📦 Cargando ELF64...");
+    /// Pasa a ejecutar `program` en el proceso actual, liberando su imagen anterior
+    pub fn execute(program: LoadedProgram) -> ! {
+        crate::process::set_address_space(program.space);
+        // El puntero de hilo de la imagen anterior ya no sirve
+        crate::task::set_fs_base(VirtAddr::new(program.thread_pointer.unwrap_or(0)));
+        
+        crate::println!("🚀 Saltando a entry point en ring 3: 0x{:x}", program.entry);
+        unsafe { usermode::enter_user_mode(program.entry, program.stack_top, program.compat) }
+    }
+    
+    /// Carga el ELF en un espacio de direcciones nuevo, sin activarlo, con
+    /// `argv` y `envp` en la pila inicial. Si pide un intérprete (PT_INTERP),
+    /// se carga también y la ejecución empieza en él. Los ELFCLASS32 de i386
+    /// se cargan por debajo de 4 GiB para el modo compatibilidad.
+    pub fn load(file: &[u8], argv: &[String], envp: &[String]) -> Result<LoadedProgram, ElfError> {
+        crate::println!("📦 Cargando ELF...");
+        let elf = Self::parse(file)?;
+        let ehdr = elf.header();
+        let compat = elf.class() == Class::Elf32;
+        if compat {
+            Self::check_compat(&elf)?;
+        }
+        
+        let base = Self::load_base(ehdr);
+        if base != 0 {
+            crate::println!("  PIE cargado en 0x{:x}", base);
+        }
+        Self::check_user_range(&elf, base)?;
+        let interpreter = Self::interpreter(&elf)?;
+        
+        // Cada programa en su propia PML4. Los segmentos se copian por el
+        // HHDM, sin activarla; si algo falla, el Drop libera lo mapeado.
+        let mut space = AddressSpace::new()?;
+        space.set_user_end(Self::space_end(&elf));
+        
+        let image_end = Self::load_segments(&elf, base, &mut space)?;
+        // Con intérprete es él quien reubica el programa
+        if ehdr.e_type == ET_DYN && interpreter.is_none() {
+            Self::relocate(&elf, base, &mut space)?;
+        }
+        crate::println!("✅ Segmentos cargados");
+        let entry = Self::entry_point(&elf, base)?;
+        
+        // El heap de `brk` empieza justo después del segmento más alto
+        space.init_brk(VirtAddr::new(image_end));
+        
+        let mut auxv = alloc::vec![
+            (AT_PHENT, ehdr.e_phentsize as u64),
+            (AT_PHNUM, ehdr.e_phnum as u64),
+            (AT_PAGESZ, 4096),
+            (AT_ENTRY, entry),
+        ];
+        if let Some(phdr) = Self::phdr_address(&elf) {
+            auxv.push((AT_PHDR, base.wrapping_add(phdr)));
+        }
+        
+        let mut start = entry;
+        if let Some(path) = interpreter {
+            start = Self::load_interpreter(path, Self::image_range(&elf, base), &mut space)?;
+            auxv.push((AT_BASE, INTERP_LOAD_BASE));
+        }
+        
+        let stack_top = if compat { COMPAT_STACK_TOP } else { USER_STACK_TOP };
+        let stack_top = usermode::map_user_stack(&mut space, stack_top)?;
+        let stack_top = usermode::build_initial_stack(&mut space, stack_top, elf.class(), argv, envp, &auxv)?;
+        
+        // Con intérprete, el TLS de todos los módulos lo monta él
+        let thread_pointer = match interpreter {
+            None => Self::setup_tls(&elf, &mut space)?,
+            Some(_) => None,
+        };
+        Ok(LoadedProgram { space, entry: start, stack_top, thread_pointer, compat })
+    }
+    
+    /// Lo que todavía no se soporta en modo compatibilidad: solo programas
+    /// estáticos con direcciones fijas y sin TLS (en i386 lo monta la libc
+    /// con `set_thread_area`, que no existe aquí)
+    fn check_compat(elf: &ElfFile) -> Result<(), ElfError> {
+        if elf.header().e_type != ET_EXEC {
+            return Err(ElfError::UnsupportedCompat("ejecutable independiente de posición"));
+        }
+        for phdr in elf.program_headers() {
+            match phdr.p_type {
+                PT_INTERP => return Err(ElfError::UnsupportedCompat("enlazado dinámicamente")),
+                PT_TLS => return Err(ElfError::UnsupportedCompat("usa TLS")),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+    
+    /// Monta el bloque TLS del hilo inicial a partir de PT_TLS, con el
+    /// esquema de x86_64 (variante II): los datos justo debajo del puntero de
+    /// hilo y, en él, el TCB, cuya primera palabra apunta a sí mismo (`%fs:0`).
+    /// Devuelve el puntero de hilo.
+    fn setup_tls(elf: &ElfFile, space: &mut AddressSpace) -> Result<Option<u64>, ElfError> {
+        let Some(tls) = elf.program_headers().find(|phdr| phdr.p_type == PT_TLS) else {
+            return Ok(None);
+        };
+        
+        // Alineación y tamaños ya comprobados en `validate_segments`
+        let align = tls.p_align.max(1);
+        if align > 4096 {
+            return Err(ElfError::BadTls);
+        }
+        let image = elf.segment_data(&tls)?;
+        
+        // Con el bloque empezando en página, el puntero de hilo queda alineado
+        let tls_size = tls.p_memsz.checked_add(align - 1)
+            .and_then(|size| (size & !(align - 1)).checked_add(TCB_SIZE))
+            .ok_or(ElfError::BadTls)? - TCB_SIZE;
+        let flags = PageTableFlags::PRESENT
+            | PageTableFlags::WRITABLE
+            | PageTableFlags::USER_ACCESSIBLE
+            | PageTableFlags::NO_EXECUTE;
+        let block = space.map_anonymous(None, tls_size + TCB_SIZE, flags)?;
+        let thread_pointer = block + tls_size;
+        
+        // La parte sin datos (.tbss) ya está a cero
+        space.write(block, image)?;
+        space.write(thread_pointer, &thread_pointer.as_u64().to_ne_bytes())?;
+        crate::println!("  TLS: {} bytes, puntero de hilo 0x{:x}", tls.p_memsz, thread_pointer.as_u64());
+        Ok(Some(thread_pointer.as_u64()))
+    }
+    
+    /// Comprueba la cabecera y los program headers antes de cargar nada
+    fn parse(file: &[u8]) -> Result<ElfFile<'_>, ElfError> {
         crate::println!("Tamaño del archivo: {} bytes", file.len());
         
-        let ehdr = Elf64_Ehdr::from_bytes(file).ok_or("Archivo demasiado pequeño")?;
+        let elf = ElfFile::parse(file)?;
+        let ehdr = elf.header();
         crate::println!("  Magic: {:02x?}", &ehdr.e_ident[0..4]);
         crate::println!("  Entry: 0x{:x}", ehdr.e_entry);
         crate::println!("  PHDRs: {}", ehdr.e_phnum);
         crate::println!("  PHDR offset: 0x{:x}", ehdr.e_phoff);
         crate::println!("  PHDR entry size: {} bytes", ehdr.e_phentsize);
         
-        if !ehdr.is_valid() {
-            return Err("No es un archivo ELF válido");
+        let machine = match elf.class() {
+            Class::Elf64 => EM_X86_64,
+            Class::Elf32 => EM_386,
+        };
+        if ehdr.e_machine != machine {
+            return Err(ElfError::WrongMachine(ehdr.e_machine));
+        }
+        if ehdr.e_type != ET_EXEC && ehdr.e_type != ET_DYN {
+            return Err(ElfError::NotExecutable(ehdr.e_type));
         }
-        
         if ehdr.e_phnum == 0 {
-            crate::println!("⚠️  ELF sin program headers");
-            return Err("ELF sin segmentos cargables");
+            return Err(ElfError::NoSegments);
+        }
+        Self::validate_segments(&elf)?;
+        Ok(elf)
+    }
+    
+    /// Comprobaciones de los segmentos que no dependen de dónde se carguen:
+    /// que sus datos estén en el archivo, que no sean a la vez escribibles y
+    /// ejecutables y que los PT_LOAD no se solapen
+    fn validate_segments(elf: &ElfFile) -> Result<(), ElfError> {
+        let mut loads = Vec::new();
+        for phdr in elf.program_headers() {
+            let needs_data = matches!(phdr.p_type, PT_LOAD | PT_DYNAMIC | PT_INTERP | PT_TLS);
+            if !needs_data {
+                continue;
+            }
+            elf.segment_data(&phdr)?;
+            if phdr.p_filesz > phdr.p_memsz && phdr.p_type != PT_INTERP {
+                return Err(ElfError::BadSegment { vaddr: phdr.p_vaddr });
+            }
+            if phdr.p_align > 1 && !phdr.p_align.is_power_of_two() {
+                return Err(if phdr.p_type == PT_TLS { ElfError::BadTls } else { ElfError::BadSegment { vaddr: phdr.p_vaddr } });
+            }
+            if phdr.p_type != PT_LOAD {
+                continue;
+            }
+            if phdr.p_flags & PF_W != 0 && phdr.p_flags & PF_X != 0 {
+                return Err(ElfError::WriteAndExecute { vaddr: phdr.p_vaddr });
+            }
+            let end = phdr.p_vaddr.checked_add(phdr.p_memsz)
+                .ok_or(ElfError::KernelHalfAddress(phdr.p_vaddr))?;
+            if phdr.p_memsz > 0 {
+                loads.push((phdr.p_vaddr, end));
+            }
+        }
+        if loads.is_empty() {
+            return Err(ElfError::NoSegments);
         }
         
-        Self::load_segments(file, ehdr)?;
-        crate::println!("✅ Segmentos cargados");
+        // Pueden compartir página, pero no bytes
+        loads.sort_unstable();
+        for pair in loads.windows(2) {
+            if pair[1].0 < pair[0].1 {
+                return Err(ElfError::OverlappingSegments { first: pair[0].0, second: pair[1].0 });
+            }
+        }
+        Ok(())
+    }
+    
+    /// Páginas que ocupa la imagen cargada en `base`: `[inicio, fin)`
+    fn image_range(elf: &ElfFile, base: u64) -> (u64, u64) {
+        elf.program_headers()
+            .filter(|phdr| phdr.p_type == PT_LOAD && phdr.p_memsz > 0)
+            .map(|phdr| (base + phdr.p_vaddr, base + phdr.p_vaddr + phdr.p_memsz))
+            .fold((u64::MAX, 0), |(start, end), (s, e)| (start.min(s & !4095), end.max((e + 4095) & !4095)))
+    }
+    
+    /// Ruta del intérprete que pide el programa (PT_INTERP), si pide uno
+    fn interpreter<'a>(elf: &ElfFile<'a>) -> Result<Option<&'a str>, ElfError> {
+        let Some(interp) = elf.program_headers().find(|phdr| phdr.p_type == PT_INTERP) else {
+            return Ok(None);
+        };
         
-        crate::println!("🚀 Saltando a entry point: 0x{:x}", ehdr.e_entry);
-        type EntryFn = extern "C" fn(&mut dyn Syscalls);
-        let entry_fn: EntryFn = unsafe { core::mem::transmute(ehdr.e_entry as usize) };
+        let bytes = elf.segment_data(&interp)?;
+        // La ruta termina en NUL
+        let path = bytes.split(|&b| b == 0).next().unwrap_or(bytes);
+        core::str::from_utf8(path)
+            .map(Some)
+            .map_err(|_| ElfError::BadInterpreter("ruta no válida"))
+    }
+    
+    /// Carga el intérprete `path` en `INTERP_LOAD_BASE` y devuelve su punto
+    /// de entrada. Se reubica él mismo, así que no se aplican relocaciones.
+    /// No hay sistema de archivos: tiene que ser uno de los módulos de
+    /// arranque (ver `programs`).
+    fn load_interpreter(path: &str, program: (u64, u64), space: &mut AddressSpace) -> Result<u64, ElfError> {
+        crate::println!("  Intérprete: {}", path);
+        let (_, file) = crate::programs::find(path).ok_or(ElfError::InterpreterNotFound)?;
+        let elf = Self::parse(file)?;
+        let ehdr = elf.header();
+        if elf.class() != Class::Elf64 {
+            return Err(ElfError::BadInterpreter("no es de 64 bits"));
+        }
+        if ehdr.e_type != ET_DYN {
+            return Err(ElfError::BadInterpreter("no es independiente de posición"));
+        }
+        if Self::interpreter(&elf)?.is_some() {
+            return Err(ElfError::BadInterpreter("pide a su vez un intérprete"));
+        }
+        Self::check_user_range(&elf, INTERP_LOAD_BASE)?;
+        let interp = Self::image_range(&elf, INTERP_LOAD_BASE);
+        if interp.0 < program.1 && program.0 < interp.1 {
+            return Err(ElfError::OverlappingSegments { first: program.0, second: interp.0 });
+        }
+        Self::load_segments(&elf, INTERP_LOAD_BASE, space)?;
+        Self::entry_point(&elf, INTERP_LOAD_BASE)
+    }
+    
+    /// Desplazamiento de la imagen: 0 para ET_EXEC, que ya lleva direcciones
+    /// absolutas, y `PIE_LOAD_BASE` para ET_DYN
+    fn load_base(ehdr: &Header) -> u64 {
+        match ehdr.e_type {
+            ET_DYN => PIE_LOAD_BASE,
+            _ => 0,
+        }
+    }
+    
+    /// Punto de entrada de la imagen cargada en `base`
+    fn entry_point(elf: &ElfFile, base: u64) -> Result<u64, ElfError> {
+        let e_entry = elf.header().e_entry;
+        base.checked_add(e_entry)
+            .filter(|&entry| entry < Self::space_end(elf))
+            .ok_or(ElfError::KernelHalfAddress(e_entry))
+    }
+    
+    /// Final de las direcciones que puede usar el programa
+    fn space_end(elf: &ElfFile) -> u64 {
+        match elf.class() {
+            Class::Elf64 => USER_SPACE_END,
+            Class::Elf32 => COMPAT_SPACE_END,
+        }
+    }
+    
+    /// Comprueba que todos los PT_LOAD, desplazados `base`, caen en la mitad
+    /// baja (o en los 4 GiB de un programa de 32 bits), sin tocar la página 0
+    /// ni la última, que se quedan sin mapear como en `map_anonymous`.
+    /// Después de esto las sumas con `base` ya no pueden desbordar.
+    fn check_user_range(elf: &ElfFile, base: u64) -> Result<(), ElfError> {
+        let space_end = Self::space_end(elf);
+        for phdr in elf.program_headers().filter(|phdr| phdr.p_type == PT_LOAD) {
+            let Some(start) = base.checked_add(phdr.p_vaddr) else {
+                return Err(ElfError::KernelHalfAddress(phdr.p_vaddr));
+            };
+            // El final se redondea a página, que es lo que acaba mapeado
+            let end = start.checked_add(phdr.p_memsz).and_then(|end| end.checked_add(4095));
+            if start < 4096 || end.is_none_or(|end| end & !4095 >= space_end) {
+                return Err(ElfError::KernelHalfAddress(phdr.p_vaddr));
+            }
+        }
+        Ok(())
+    }
+    
+    /// Dirección en memoria de los program headers (para `AT_PHDR`): la de
+    /// PT_PHDR, o la que les corresponde dentro del segmento que los carga
+    fn phdr_address(elf: &ElfFile) -> Option<u64> {
+        let phoff = elf.header().e_phoff;
+        let mut loaded = None;
+        for phdr in elf.program_headers() {
+            if phdr.p_type == PT_PHDR {
+                return Some(phdr.p_vaddr);
+            }
+            if phdr.p_type == PT_LOAD
+                && phdr.p_offset <= phoff
+                && phoff < phdr.p_offset.saturating_add(phdr.p_filesz)
+            {
+                loaded.get_or_insert(phdr.p_vaddr + (phoff - phdr.p_offset));
+            }
+        }
+        loaded
+    }
+    
+    /// Posición en el archivo de los `len` bytes que se cargan en `vaddr`
+    /// (sin desplazar), si están enteros en los datos de un PT_LOAD
+    fn vaddr_to_offset(elf: &ElfFile, vaddr: u64, len: u64) -> Option<u64> {
+        let offset = elf.program_headers()
+            .filter(|phdr| phdr.p_type == PT_LOAD && phdr.p_vaddr <= vaddr)
+            .find(|phdr| vaddr.checked_add(len).is_some_and(|end| end <= phdr.p_vaddr.saturating_add(phdr.p_filesz)))
+            .map(|phdr| phdr.p_offset + (vaddr - phdr.p_vaddr))?;
+        let end = offset.checked_add(len)?;
+        (end <= elf.data().len() as u64).then_some(offset)
+    }
+    
+    /// Aplica las relocaciones de PT_DYNAMIC a una imagen cargada en `base`.
+    /// Un PIE estático solo necesita R_X86_64_RELATIVE: `base + addend`.
+    fn relocate(elf: &ElfFile, base: u64, space: &mut AddressSpace) -> Result<(), ElfError> {
+        let Some(dynamic) = elf.program_headers().find(|phdr| phdr.p_type == PT_DYNAMIC) else {
+            return Ok(());
+        };
+        elf.segment_data(&dynamic)?;
         
-        entry_fn(syscalls);
+        let (mut rela, mut rela_size, mut rela_entry) = (None, 0, size_of::<Elf64_Rela>() as u64);
+        let entries = dynamic.p_filesz / size_of::<Elf64_Dyn>() as u64;
+        for i in 0..entries {
+            let offset = dynamic.p_offset + i * size_of::<Elf64_Dyn>() as u64;
+            let entry: Elf64_Dyn = elf.read(offset).ok_or(ElfError::Truncated)?;
+            match entry.d_tag {
+                DT_NULL => break,
+                DT_RELA => rela = Some(entry.d_val),
+                DT_RELASZ => rela_size = entry.d_val,
+                DT_RELAENT => rela_entry = entry.d_val,
+                DT_REL => return Err(ElfError::BadDynamic("relocaciones sin sumando (DT_REL)")),
+                _ => {}
+            }
+        }
+        let Some(rela) = rela else { return Ok(()) };
+        if rela_entry != size_of::<Elf64_Rela>() as u64 {
+            return Err(ElfError::BadDynamic("tamaño de relocación no válido"));
+        }
         
+        let offset = Self::vaddr_to_offset(elf, rela, rela_size)
+            .ok_or(ElfError::BadDynamic("tabla de relocaciones fuera del archivo"))?;
+        let count = rela_size / rela_entry;
+        for i in 0..count {
+            let entry: Elf64_Rela = elf.read(offset + i * rela_entry).ok_or(ElfError::Truncated)?;
+            match entry.r_type() {
+                R_X86_64_NONE => {}
+                R_X86_64_RELATIVE => {
+                    let target = base.checked_add(entry.r_offset)
+                        .and_then(|target| VirtAddr::try_new(target).ok())
+                        .ok_or(ElfError::BadDynamic("relocación fuera del espacio de usuario"))?;
+                    let value = base.wrapping_add(entry.r_addend as u64);
+                    space.write(target, &value.to_ne_bytes())?;
+                }
+                kind => return Err(ElfError::UnsupportedRelocation(kind)),
+            }
+        }
+        crate::println!("  {} relocaciones aplicadas", count);
         Ok(())
     }
     
-    fn load_segments(file: &[u8], ehdr: &Elf64_Ehdr) -> Result<(), &'static str> {
-        let phoff = ehdr.e_phoff as usize;
-        let phentsize = ehdr.e_phentsize as usize;
-        let phnum = ehdr.e_phnum as usize;
+    /// Carga los segmentos PT_LOAD desplazados `base` bytes y devuelve el
+    /// final del más alto
+    fn load_segments(elf: &ElfFile, base: u64, space: &mut AddressSpace) -> Result<u64, ElfError> {
+        let mut image_end = 0;
         
-        if file.len() < phoff + phnum * phentsize {
-            return Err("Program headers fuera del archivo");
-        }
+        // Los segmentos se copian con las páginas escribibles y sin ejecución;
+        // los permisos definitivos se aplican al final, cuando ya se sabe qué
+        // segmentos comparten cada página.
+        let mut final_flags = BTreeMap::new();
         
-        for i in 0..phnum {
-            let phdr_ptr = (file.as_ptr() as usize + phoff + i * phentsize) as *const Elf64_Phdr;
-            let phdr = unsafe { &*phdr_ptr };
-            
+        for phdr in elf.program_headers() {
             if phdr.p_type != PT_LOAD {
                 crate::println!("  Segmento tipo {} ignorado", phdr.p_type);
                 continue;
             }
             
-            Self::load_segment(file, phdr)?;
+            Self::load_segment(elf, &phdr, base, space, &mut final_flags)?;
+            image_end = image_end.max(base + phdr.p_vaddr + phdr.p_memsz);
         }
         
-        Ok(())
+        for (&page, &flags) in &final_flags {
+            if flags.contains(PageTableFlags::WRITABLE) && !flags.contains(PageTableFlags::NO_EXECUTE) {
+                return Err(ElfError::WriteAndExecute { vaddr: page });
+            }
+            space.protect_range(VirtAddr::new(page), 1, flags)
+                .map_err(|_| ElfError::Memory("Error al proteger memoria"))?;
+        }
+        
+        Ok(image_end)
+    }
+    
+    /// Flags de página para los permisos de un segmento
+    fn segment_flags(p_flags: u32) -> PageTableFlags {
+        let mut flags = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+        if p_flags & PF_W != 0 { flags |= PageTableFlags::WRITABLE; }
+        if p_flags & PF_X == 0 { flags |= PageTableFlags::NO_EXECUTE; }
+        flags
     }
     
-    fn load_segment(file: &[u8], phdr: &Elf64_Phdr) -> Result<(), &'static str> {
-        let vaddr = phdr.p_vaddr as usize;
-        let offset = phdr.p_offset as usize;
+    /// Unión de permisos de dos segmentos que comparten página: NO_EXECUTE
+    /// quita un permiso, así que solo se queda si ambos lo tienen.
+    fn merge_flags(a: PageTableFlags, b: PageTableFlags) -> PageTableFlags {
+        let no_execute = a & b & PageTableFlags::NO_EXECUTE;
+        ((a | b) - PageTableFlags::NO_EXECUTE) | no_execute
+    }
+    
+    fn load_segment(
+        elf: &ElfFile,
+        phdr: &ProgramHeader,
+        base: u64,
+        space: &mut AddressSpace,
+        final_flags: &mut BTreeMap<u64, PageTableFlags>,
+    ) -> Result<(), ElfError> {
+        // Rango, tamaños y permisos ya comprobados en `parse` y `check_user_range`
+        let vaddr = (base + phdr.p_vaddr) as usize;
         let filesz = phdr.p_filesz as usize;
         let memsz = phdr.p_memsz as usize;
         
         crate::println!("  Cargando segmento LOAD en 0x{:x} ({} bytes)", vaddr, memsz);
         crate::println!("    flags: {}{}{}", 
-            if phdr.p_flags & 1 != 0 { "X" } else { "-" },
-            if phdr.p_flags & 2 != 0 { "W" } else { "-" },
-            if phdr.p_flags & 4 != 0 { "R" } else { "-" });
+            if phdr.p_flags & PF_X != 0 { "X" } else { "-" },
+            if phdr.p_flags & PF_W != 0 { "W" } else { "-" },
+            if phdr.p_flags & PF_R != 0 { "R" } else { "-" });
         
-        // Calcular páginas necesarias
-        let page_count = (memsz + 4095) / 4096;
+        // Solo se mapean ya las páginas con datos del archivo; el resto del BSS
+        // queda como región perezosa y se respalda en el primer acceso.
         let virt_start = VirtAddr::new(vaddr as u64);
+        let page_start = virt_start.align_down(4096u64);
+        let file_end = (virt_start + filesz as u64).align_up(4096u64);
+        let mem_end = (virt_start + memsz as u64).align_up(4096u64);
+        let page_count = (file_end - page_start) / 4096;
+        
+        let flags = Self::segment_flags(phdr.p_flags);
+        let copy_flags = PageTableFlags::PRESENT
+            | PageTableFlags::USER_ACCESSIBLE
+            | PageTableFlags::WRITABLE
+            | PageTableFlags::NO_EXECUTE;
         
-        // Determinar flags de página
-        let mut flags = PageTableFlags::PRESENT;
-        if phdr.p_flags & 2 != 0 { flags |= PageTableFlags::WRITABLE; }
+        // Mapear memoria. Dos segmentos pueden compartir página (el final de
+        // uno y el principio del siguiente): esa página ya está mapeada y se
+        // queda con la unión de los permisos.
+        for i in 0..page_count {
+            let page = page_start + i * 4096;
+            if space.page_flags(page).is_none() {
+                space.map_range(page, 1, copy_flags)
+                    .map_err(|_| ElfError::Memory("Error al mapear memoria"))?;
+            }
+            let merged = match final_flags.get(&page.as_u64()) {
+                Some(&existing) => Self::merge_flags(existing, flags),
+                None => flags,
+            };
+            final_flags.insert(page.as_u64(), merged);
+        }
         
-        // Mapear memoria
-        map_range(virt_start, page_count as u64, flags)
-            .map_err(|_| "Error al mapear memoria")?;
+        if mem_end > file_end {
+            crate::println!("    BSS perezoso: {} páginas", (mem_end - file_end) / 4096);
+            space.add_lazy_region(file_end, mem_end, flags, RegionKind::Bss);
+        }
         
-        // Destino
-        let dest = virt_start.as_mut_ptr();
+        // Copiar datos
+        if filesz > 0 {
+            let src = elf.segment_data(phdr)?;
+            crate::println!("    copiando {} bytes", filesz);
+            space.write(virt_start, src)?;
+        }
         
-        unsafe {
-            // Copiar datos
-            if filesz > 0 {
-                if file.len() < offset + filesz {
-                    return Err("Segmento fuera del archivo");
-                }
-                let src = &file[offset..offset + filesz];
-                crate::println!("    copiando {} bytes", filesz);
-                core::ptr::copy_nonoverlapping(src.as_ptr(), dest, filesz);
-            }
-            
-            // Limpiar BSS
-            if memsz > filesz {
-                let bss_start = dest.add(filesz);
-                let bss_size = memsz - filesz;
-                crate::println!("    limpiando BSS: {} bytes", bss_size);
-                core::ptr::write_bytes(bss_start, 0, bss_size);
-            }
+        // Limpiar la parte del BSS que comparte página con los datos
+        let bss_end = (vaddr + memsz).min(file_end.as_u64() as usize);
+        if bss_end > vaddr + filesz {
+            let bss_size = bss_end - (vaddr + filesz);
+            crate::println!("    limpiando BSS: {} bytes", bss_size);
+            space.zero(virt_start + filesz as u64, bss_size)?;
         }
         
         Ok(())
@@ -1,6 +1,12 @@
 // src/elf/mod.rs
+mod error;
+mod file;
+mod header32;
 mod header64;
 mod loader64;
 mod types;
 
-pub use loader64::ElfLoader;
+pub use error::ElfError;
+pub use file::{Class, ElfFile};
+pub use loader64::{ElfLoader, LoadedProgram};
+pub use types::{AT_NULL, AT_RANDOM};
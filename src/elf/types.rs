@@ -1,4 +1,21 @@
 // src/elf/types.rs
+// Índices de e_ident y sus valores
+pub const EI_CLASS: usize = 4;
+pub const EI_DATA: usize = 5;
+pub const EI_VERSION: usize = 6;
+pub const ELFCLASS32: u8 = 1;
+pub const ELFCLASS64: u8 = 2;
+pub const ELFDATA2LSB: u8 = 1;
+pub const EV_CURRENT: u8 = 1;
+
+// Arquitecturas (e_machine)
+pub const EM_386: u16 = 3;
+pub const EM_X86_64: u16 = 62;
+
+// Tipos de archivo (e_type)
+pub const ET_EXEC: u16 = 2;
+pub const ET_DYN: u16 = 3;
+
 // Tipos de segmento
 pub const PT_NULL: u32 = 0;
 pub const PT_LOAD: u32 = 1;
@@ -7,8 +24,46 @@ pub const PT_INTERP: u32 = 3;
 pub const PT_NOTE: u32 = 4;
 pub const PT_SHLIB: u32 = 5;
 pub const PT_PHDR: u32 = 6;
+pub const PT_TLS: u32 = 7;
 
 // Flags de segmento
 pub const PF_X: u32 = 1;
 pub const PF_W: u32 = 2;
 pub const PF_R: u32 = 4;
+
+// Tipos de sección
+pub const SHT_NULL: u32 = 0;
+pub const SHT_PROGBITS: u32 = 1;
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_STRTAB: u32 = 3;
+pub const SHT_NOTE: u32 = 7;
+pub const SHT_NOBITS: u32 = 8;
+pub const SHT_DYNSYM: u32 = 11;
+
+// Índices especiales de sección
+pub const SHN_UNDEF: u16 = 0;
+
+// Tipos de símbolo (los 4 bits bajos de st_info)
+pub const STT_OBJECT: u8 = 1;
+pub const STT_FUNC: u8 = 2;
+
+// Entradas del vector auxiliar (auxv) de la pila inicial
+pub const AT_NULL: u64 = 0;
+pub const AT_PHDR: u64 = 3;
+pub const AT_PHENT: u64 = 4;
+pub const AT_PHNUM: u64 = 5;
+pub const AT_PAGESZ: u64 = 6;
+pub const AT_BASE: u64 = 7;
+pub const AT_ENTRY: u64 = 9;
+pub const AT_RANDOM: u64 = 25;
+
+// Etiquetas de PT_DYNAMIC
+pub const DT_NULL: i64 = 0;
+pub const DT_RELA: i64 = 7;
+pub const DT_RELASZ: i64 = 8;
+pub const DT_RELAENT: i64 = 9;
+pub const DT_REL: i64 = 17;
+
+// Tipos de relocación de x86_64
+pub const R_X86_64_NONE: u32 = 0;
+pub const R_X86_64_RELATIVE: u32 = 8;
@@ -0,0 +1,121 @@
+// src/file.rs
+//! Archivos abiertos y tabla de descriptores de cada proceso
+//!
+//! Todavía no hay sistema de archivos: se puede abrir la consola y los
+//! programas cargados como módulos (de solo lectura, ver `programs`).
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crate::syscall::Errno;
+
+/// Flags de `open` (los de Linux)
+pub const O_ACCMODE: u32 = 0o3;
+pub const O_RDONLY: u32 = 0o0;
+
+/// Descriptores con los que empieza todo proceso: stdin, stdout y stderr
+const STD_FDS: usize = 3;
+
+/// Límite de descriptores abiertos por proceso
+const MAX_FDS: usize = 64;
+
+pub enum File {
+    /// Lee líneas del teclado y escribe en el framebuffer
+    Console,
+    /// Programa cargado por Limine como módulo
+    Program { data: &'static [u8], offset: AtomicUsize },
+}
+
+impl File {
+    /// Puede bloquear (la consola espera a que se escriba una línea)
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize, Errno> {
+        match self {
+            File::Console => Ok(crate::keyboard::read(buf)),
+            File::Program { data, offset } => {
+                let start = offset.load(Ordering::Relaxed).min(data.len());
+                let count = buf.len().min(data.len() - start);
+                buf[..count].copy_from_slice(&data[start..start + count]);
+                offset.store(start + count, Ordering::Relaxed);
+                Ok(count)
+            }
+        }
+    }
+
+    /// Contenido entero, si se puede mapear con `mmap` (solo los programas)
+    pub fn contents(&self) -> Option<&'static [u8]> {
+        match self {
+            File::Console => None,
+            File::Program { data, .. } => Some(data),
+        }
+    }
+
+    pub fn write(&self, buf: &[u8]) -> Result<usize, Errno> {
+        match self {
+            File::Console => {
+                if let Ok(s) = core::str::from_utf8(buf) {
+                    crate::print!("{}", s);
+                }
+                Ok(buf.len())
+            }
+            // Solo se pueden abrir con O_RDONLY
+            File::Program { .. } => Err(Errno::EBADF),
+        }
+    }
+}
+
+/// Abre `path`: `/dev/console` o uno de los módulos de arranque
+pub fn open(path: &str, flags: u32) -> Result<File, Errno> {
+    if path == "/dev/console" {
+        return Ok(File::Console);
+    }
+    let (_, data) = crate::programs::find(path).ok_or(Errno::ENOENT)?;
+    if flags & O_ACCMODE != O_RDONLY {
+        return Err(Errno::EROFS);
+    }
+    Ok(File::Program { data, offset: AtomicUsize::new(0) })
+}
+
+/// Descriptores de un proceso. Los archivos van en `Arc` para poder usarlos
+/// sin la tabla de procesos cogida (una lectura de la consola bloquea) y
+/// para que padre e hijo compartan la posición tras un `fork`.
+#[derive(Clone)]
+pub struct FdTable {
+    files: Vec<Option<Arc<File>>>,
+}
+
+impl FdTable {
+    /// Tabla con stdin, stdout y stderr apuntando a la consola
+    pub fn new() -> Self {
+        let console = Arc::new(File::Console);
+        FdTable { files: (0..STD_FDS).map(|_| Some(console.clone())).collect() }
+    }
+
+    pub fn get(&self, fd: u32) -> Option<Arc<File>> {
+        self.files.get(fd as usize)?.clone()
+    }
+
+    /// Guarda `file` en el descriptor libre más bajo y lo devuelve
+    pub fn insert(&mut self, file: File) -> Result<u32, Errno> {
+        let file = Some(Arc::new(file));
+        if let Some(fd) = self.files.iter().position(Option::is_none) {
+            self.files[fd] = file;
+            return Ok(fd as u32);
+        }
+        if self.files.len() >= MAX_FDS {
+            return Err(Errno::EMFILE);
+        }
+        self.files.push(file);
+        Ok(self.files.len() as u32 - 1)
+    }
+
+    pub fn close_all(&mut self) {
+        self.files.clear();
+    }
+
+    pub fn close(&mut self, fd: u32) -> Result<(), Errno> {
+        self.files.get_mut(fd as usize)
+            .and_then(Option::take)
+            .map(|_| ())
+            .ok_or(Errno::EBADF)
+    }
+}
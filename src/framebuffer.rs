@@ -1,5 +1,5 @@
 use core::fmt;
-use spin::Mutex;
+use crate::sync::IrqMutex;
 use crate::font;
 
 #[derive(Clone, Copy, Debug)]
@@ -186,7 +186,7 @@ impl fmt::Write for Framebuffer {
 }
 
 // Writer global
-pub static WRITER: Mutex<Option<Framebuffer>> = Mutex::new(None);
+pub static WRITER: IrqMutex<Option<Framebuffer>> = IrqMutex::new(None);
 pub const INPUT_PROMPT: &str = "> ";
 
 #[macro_export]
@@ -208,3 +208,8 @@ pub fn _print(args: fmt::Arguments) {
         fb.write_fmt(args).unwrap();
     }
 }
+
+/// Tamaño de la consola en caracteres (columnas, filas)
+pub fn text_size() -> (usize, usize) {
+    WRITER.lock().as_ref().map_or((0, 0), |fb| (fb.width / 8, fb.height / 16))
+}
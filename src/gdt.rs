@@ -0,0 +1,84 @@
+// src/gdt.rs
+//! GDT propia del kernel. La de Limine no tiene segmentos de usuario, y
+//! SYSCALL/SYSRET exigen un orden concreto de selectores (ver `STAR`).
+
+use core::ptr::{addr_of, addr_of_mut};
+use x86_64::structures::gdt::{GlobalDescriptorTable, Descriptor, DescriptorFlags, SegmentSelector};
+use x86_64::structures::tss::TaskStateSegment;
+use x86_64::instructions::segmentation::{Segment, CS, DS, ES, SS};
+use x86_64::instructions::tables::load_tss;
+use x86_64::VirtAddr;
+use spin::Lazy;
+
+/// Entrada de la IST usada por el double fault (la CPU usa `índice + 1`)
+pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
+
+const STACK_SIZE: usize = 20 * 1024;
+
+// Pila limpia para el double fault: si el fallo viene de un desbordamiento
+// de pila del kernel, no podemos seguir usando la misma.
+static mut DOUBLE_FAULT_STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+
+// Pila a la que salta la CPU cuando una interrupción llega desde ring 3
+static mut PRIVILEGE_STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+
+static mut TSS: TaskStateSegment = TaskStateSegment::new();
+
+pub struct Selectors {
+    pub kernel_code: SegmentSelector,
+    pub kernel_data: SegmentSelector,
+    /// Código de 32 bits (modo compatibilidad) para los programas i386
+    pub user_code32: SegmentSelector,
+    pub user_data: SegmentSelector,
+    pub user_code: SegmentSelector,
+    pub tss: SegmentSelector,
+}
+
+// El orden importa: SYSRET carga SS = base + 8 y CS = base + 16, así que
+// user_data tiene que ir justo antes de user_code. El código de 32 bits va
+// en `base`, donde lo espera SYSRET hacia modo compatibilidad (como en Linux).
+static GDT: Lazy<(GlobalDescriptorTable, Selectors)> = Lazy::new(|| {
+    let mut gdt = GlobalDescriptorTable::new();
+    let kernel_code = gdt.add_entry(Descriptor::kernel_code_segment());
+    let kernel_data = gdt.add_entry(Descriptor::kernel_data_segment());
+    let user_code32 = gdt.add_entry(Descriptor::UserSegment(DescriptorFlags::USER_CODE32.bits()));
+    let user_data = gdt.add_entry(Descriptor::user_data_segment());
+    let user_code = gdt.add_entry(Descriptor::user_code_segment());
+    // SAFETY: TSS es un static, vive todo lo que vive la GDT
+    let tss = gdt.add_entry(unsafe { Descriptor::tss_segment_unchecked(addr_of!(TSS)) });
+    (gdt, Selectors { kernel_code, kernel_data, user_code32, user_data, user_code, tss })
+});
+
+fn stack_top(stack: *const [u8; STACK_SIZE]) -> VirtAddr {
+    // Alineada a 16 como pide la ABI
+    VirtAddr::new(stack as u64 + STACK_SIZE as u64).align_down(16u64)
+}
+
+pub fn init() {
+    unsafe {
+        let tss = &mut *addr_of_mut!(TSS);
+        tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] =
+            stack_top(addr_of!(DOUBLE_FAULT_STACK));
+        tss.privilege_stack_table[0] = stack_top(addr_of!(PRIVILEGE_STACK));
+    }
+
+    GDT.0.load();
+    let selectors = &GDT.1;
+    unsafe {
+        CS::set_reg(selectors.kernel_code);
+        SS::set_reg(selectors.kernel_data);
+        DS::set_reg(selectors.kernel_data);
+        ES::set_reg(selectors.kernel_data);
+        load_tss(selectors.tss);
+    }
+}
+
+pub fn selectors() -> &'static Selectors {
+    &GDT.1
+}
+
+/// Pila a la que salta la CPU al recibir una interrupción desde ring 3
+/// (la cambia el planificador en cada cambio de tarea)
+pub fn set_kernel_stack(top: VirtAddr) {
+    unsafe { (*addr_of_mut!(TSS)).privilege_stack_table[0] = top };
+}
@@ -0,0 +1,142 @@
+//! Manejadores de las 32 excepciones de la CPU
+
+use x86_64::registers::control::Cr2;
+use x86_64::VirtAddr;
+use crate::framebuffer::WRITER;
+use crate::usermode::{self, USER_SPACE_END};
+use crate::syscall::{SIGBUS, SIGFPE, SIGILL, SIGSEGV, SIGTRAP};
+use super::InterruptFrame;
+
+pub const BREAKPOINT: usize = 3;
+pub const DOUBLE_FAULT: usize = 8;
+pub const PAGE_FAULT: usize = 14;
+
+const EXCEPTION_NAMES: [&str; 32] = [
+    "Division Error",
+    "Debug",
+    "Non-Maskable Interrupt",
+    "Breakpoint",
+    "Overflow",
+    "Bound Range Exceeded",
+    "Invalid Opcode",
+    "Device Not Available",
+    "Double Fault",
+    "Coprocessor Segment Overrun",
+    "Invalid TSS",
+    "Segment Not Present",
+    "Stack-Segment Fault",
+    "General Protection Fault",
+    "Page Fault",
+    "Reserved",
+    "x87 Floating-Point Exception",
+    "Alignment Check",
+    "Machine Check",
+    "SIMD Floating-Point Exception",
+    "Virtualization Exception",
+    "Control Protection Exception",
+    "Reserved",
+    "Reserved",
+    "Reserved",
+    "Reserved",
+    "Reserved",
+    "Reserved",
+    "Hypervisor Injection Exception",
+    "VMM Communication Exception",
+    "Security Exception",
+    "Reserved",
+];
+
+pub fn handle(frame: &mut InterruptFrame) {
+    let vector = frame.vector as usize;
+
+    if vector == BREAKPOINT {
+        crate::println!("🔎 Breakpoint en {:#018x}", frame.rip);
+        return;
+    }
+
+    if vector == PAGE_FAULT && handle_page_fault(frame) {
+        return;
+    }
+
+    // Un fallo de un programa solo acaba con él; `halt` es para el kernel
+    if frame.is_user_mode() {
+        usermode::kill_current(signal_for(vector), format_args!("excepción {} ({}) en rip {:#x}",
+            vector, EXCEPTION_NAMES[vector], frame.rip));
+    }
+
+    // Si la excepción saltó con el framebuffer bloqueado no podríamos
+    // imprimir nada; no vamos a volver a ese código, así que lo liberamos.
+    if WRITER.is_locked() {
+        unsafe { WRITER.force_unlock() };
+    }
+
+    crate::println!("");
+    crate::println!("💥 EXCEPCIÓN {} ({}) en {}", vector, EXCEPTION_NAMES[vector],
+        if frame.is_user_mode() { "ring 3" } else { "el kernel" });
+    crate::println!("  error code: {:#x}", frame.error_code);
+    if vector == PAGE_FAULT {
+        crate::println!("  CR2: {:#018x}", Cr2::read_raw());
+    }
+    dump_frame(frame);
+    halt();
+}
+
+/// Señal con la que Linux termina un programa que provoca `vector`
+fn signal_for(vector: usize) -> u8 {
+    match vector {
+        // Division Error, x87 y SIMD
+        0 | 16 | 19 => SIGFPE,
+        1 | BREAKPOINT => SIGTRAP,
+        6 => SIGILL,
+        17 => SIGBUS,
+        _ => SIGSEGV,
+    }
+}
+
+// Bits del código de error de un page fault
+const PF_PRESENT: u64 = 1 << 0;
+const PF_WRITE: u64 = 1 << 1;
+
+/// Paginación bajo demanda y copy-on-write. Devuelve `true` si el fallo
+/// quedó resuelto; si lo provocó un programa con un acceso inválido, el
+/// programa muere aquí.
+fn handle_page_fault(frame: &InterruptFrame) -> bool {
+    let addr = Cr2::read_raw();
+    let write = frame.error_code & PF_WRITE != 0;
+
+    // Una lectura de una página presente nunca se puede resolver
+    if addr < USER_SPACE_END && (frame.error_code & PF_PRESENT == 0 || write) {
+        let resolved = crate::process::try_with_current_space(|space| {
+            space.handle_page_fault(VirtAddr::new(addr), write).is_ok()
+        });
+        if resolved == Some(true) {
+            return true;
+        }
+    }
+
+    if frame.is_user_mode() {
+        usermode::kill_current(SIGSEGV, format_args!(
+            "acceso inválido ({}) a {:#x} desde rip {:#x}",
+            if write { "escritura" } else { "lectura" },
+            addr, frame.rip));
+    }
+    false
+}
+
+fn dump_frame(frame: &InterruptFrame) {
+    crate::println!("  RIP={:#018x} CS={:#06x} RFLAGS={:#018x}", frame.rip, frame.cs, frame.rflags);
+    crate::println!("  RSP={:#018x} SS={:#06x}", frame.rsp, frame.ss);
+    crate::println!("  RAX={:#018x} RBX={:#018x} RCX={:#018x}", frame.rax, frame.rbx, frame.rcx);
+    crate::println!("  RDX={:#018x} RSI={:#018x} RDI={:#018x}", frame.rdx, frame.rsi, frame.rdi);
+    crate::println!("  RBP={:#018x} R8 ={:#018x} R9 ={:#018x}", frame.rbp, frame.r8, frame.r9);
+    crate::println!("  R10={:#018x} R11={:#018x} R12={:#018x}", frame.r10, frame.r11, frame.r12);
+    crate::println!("  R13={:#018x} R14={:#018x} R15={:#018x}", frame.r13, frame.r14, frame.r15);
+}
+
+fn halt() -> ! {
+    crate::println!("Sistema detenido.");
+    x86_64::instructions::interrupts::disable();
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
@@ -0,0 +1,137 @@
+// src/interrupts/mod.rs
+//! IDT y punto de entrada común de interrupciones y excepciones
+
+mod exceptions;
+mod pic;
+mod stubs;
+mod timer;
+
+use core::mem::size_of;
+use spin::Mutex;
+use x86_64::instructions::tables::lidt;
+use x86_64::structures::DescriptorTablePointer;
+use x86_64::VirtAddr;
+use stubs::{isr_stub_128, isr_stub_table, STUB_COUNT, SYSCALL_VECTOR};
+
+pub use timer::{ticks, ms_to_ticks};
+
+/// Registros tal y como los deja `isr_common` en la pila
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct InterruptFrame {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rbp: u64,
+    pub rdi: u64,
+    pub rsi: u64,
+    pub rdx: u64,
+    pub rcx: u64,
+    pub rbx: u64,
+    pub rax: u64,
+    pub vector: u64,
+    pub error_code: u64,
+    // Lo que empuja la CPU
+    pub rip: u64,
+    pub cs: u64,
+    pub rflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+}
+
+impl InterruptFrame {
+    /// La interrupción llegó mientras se ejecutaba código de usuario
+    pub fn is_user_mode(&self) -> bool {
+        self.cs & 3 == 3
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct IdtEntry {
+    offset_low: u16,
+    selector: u16,
+    ist: u8,
+    attributes: u8,
+    offset_mid: u16,
+    offset_high: u32,
+    reserved: u32,
+}
+
+impl IdtEntry {
+    const fn missing() -> Self {
+        IdtEntry {
+            offset_low: 0,
+            selector: 0,
+            ist: 0,
+            attributes: 0,
+            offset_mid: 0,
+            offset_high: 0,
+            reserved: 0,
+        }
+    }
+
+    // Interrupt gate de 64 bits presente (0x8E); `dpl` decide desde qué
+    // anillo se puede lanzar con `int`.
+    fn new(handler: u64, selector: u16, ist: u8, dpl: u8) -> Self {
+        IdtEntry {
+            offset_low: handler as u16,
+            selector,
+            ist,
+            attributes: 0x8E | (dpl << 5),
+            offset_mid: (handler >> 16) as u16,
+            offset_high: (handler >> 32) as u32,
+            reserved: 0,
+        }
+    }
+}
+
+static IDT: Mutex<[IdtEntry; 256]> = Mutex::new([IdtEntry::missing(); 256]);
+
+/// Instala la IDT y programa el PIC y el PIT. Requiere `gdt::init()` antes
+/// (usa su selector y la IST). Las interrupciones siguen desactivadas.
+pub fn init() {
+    let code_selector = crate::gdt::selectors().kernel_code.0;
+    let mut idt = IDT.lock();
+
+    for vector in 0..STUB_COUNT {
+        let handler = unsafe { isr_stub_table[vector] };
+        let ist = if vector == exceptions::DOUBLE_FAULT {
+            crate::gdt::DOUBLE_FAULT_IST_INDEX as u8 + 1
+        } else {
+            0
+        };
+        idt[vector] = IdtEntry::new(handler, code_selector, ist, 0);
+    }
+    // Con DPL 3 para que los programas i386 puedan lanzarla con `int`
+    idt[SYSCALL_VECTOR] = IdtEntry::new(isr_stub_128 as usize as u64, code_selector, 0, 3);
+
+    let pointer = DescriptorTablePointer {
+        limit: (size_of::<[IdtEntry; 256]>() - 1) as u16,
+        base: VirtAddr::new(idt.as_ptr() as u64),
+    };
+    // SAFETY: la tabla vive en un static, así que su dirección no cambia
+    unsafe { lidt(&pointer) };
+
+    pic::init();
+    timer::init();
+}
+
+extern "C" fn interrupt_dispatch(frame: &mut InterruptFrame) {
+    match frame.vector as usize {
+        0..=31 => exceptions::handle(frame),
+        pic::TIMER_VECTOR => timer::handle(),
+        pic::KEYBOARD_VECTOR => {
+            crate::keyboard::poll_keyboard();
+            pic::end_of_interrupt(1);
+        }
+        vector @ 33..=47 => pic::end_of_interrupt(vector as u8 - pic::PIC_OFFSET),
+        SYSCALL_VECTOR if frame.is_user_mode() => crate::syscall::handle_int80(frame),
+        vector => crate::println!("⚠️  Interrupción {} sin manejador", vector),
+    }
+}
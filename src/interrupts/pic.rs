@@ -0,0 +1,48 @@
+//! PIC 8259 (maestro y esclavo) remapeado a los vectores 32..48
+
+use x86_64::instructions::port::Port;
+
+pub const PIC_OFFSET: u8 = 32;
+pub const TIMER_VECTOR: usize = PIC_OFFSET as usize;
+pub const KEYBOARD_VECTOR: usize = PIC_OFFSET as usize + 1;
+
+const MASTER_COMMAND: u16 = 0x20;
+const MASTER_DATA: u16 = 0x21;
+const SLAVE_COMMAND: u16 = 0xA0;
+const SLAVE_DATA: u16 = 0xA1;
+
+const ICW1_INIT: u8 = 0x11;
+const ICW4_8086: u8 = 0x01;
+const EOI: u8 = 0x20;
+
+/// Remapea el PIC y deja sin enmascarar solo la IRQ 0 (PIT) y la 1 (teclado)
+pub fn init() {
+    unsafe {
+        let mut master_command: Port<u8> = Port::new(MASTER_COMMAND);
+        let mut master_data: Port<u8> = Port::new(MASTER_DATA);
+        let mut slave_command: Port<u8> = Port::new(SLAVE_COMMAND);
+        let mut slave_data: Port<u8> = Port::new(SLAVE_DATA);
+
+        master_command.write(ICW1_INIT);
+        slave_command.write(ICW1_INIT);
+        master_data.write(PIC_OFFSET);
+        slave_data.write(PIC_OFFSET + 8);
+        // El esclavo cuelga de la IRQ 2 del maestro
+        master_data.write(4);
+        slave_data.write(2);
+        master_data.write(ICW4_8086);
+        slave_data.write(ICW4_8086);
+
+        master_data.write(0xFC);
+        slave_data.write(0xFF);
+    }
+}
+
+pub fn end_of_interrupt(irq: u8) {
+    unsafe {
+        if irq >= 8 {
+            Port::<u8>::new(SLAVE_COMMAND).write(EOI);
+        }
+        Port::<u8>::new(MASTER_COMMAND).write(EOI);
+    }
+}
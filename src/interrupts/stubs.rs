@@ -0,0 +1,123 @@
+//! Stubs en ensamblador de cada vector
+//!
+//! Todos dejan la pila con la forma de `InterruptFrame` (los vectores sin
+//! código de error empujan un 0 para igualarla) y saltan a `interrupt_dispatch`.
+
+use core::arch::global_asm;
+use super::interrupt_dispatch;
+
+/// Número de vectores con stub: las 32 excepciones de la CPU y las 16 IRQ
+/// del PIC remapeadas a partir del 32
+pub const STUB_COUNT: usize = 48;
+
+/// Vector de `int 0x80`, la entrada de syscalls de los programas i386
+pub const SYSCALL_VECTOR: usize = 0x80;
+
+extern "C" {
+    pub static isr_stub_table: [u64; STUB_COUNT];
+    /// Stub de `SYSCALL_VECTOR`, fuera de la tabla
+    pub fn isr_stub_128();
+}
+
+global_asm!(
+    ".macro isr_noerr vec",
+    "isr_stub_\\vec:",
+    "    push 0",
+    "    push \\vec",
+    "    jmp isr_common",
+    ".endm",
+    "",
+    ".macro isr_err vec",
+    "isr_stub_\\vec:",
+    "    push \\vec",
+    "    jmp isr_common",
+    ".endm",
+    "",
+    "isr_noerr 0",
+    "isr_noerr 1",
+    "isr_noerr 2",
+    "isr_noerr 3",
+    "isr_noerr 4",
+    "isr_noerr 5",
+    "isr_noerr 6",
+    "isr_noerr 7",
+    "isr_err   8",
+    "isr_noerr 9",
+    "isr_err   10",
+    "isr_err   11",
+    "isr_err   12",
+    "isr_err   13",
+    "isr_err   14",
+    "isr_noerr 15",
+    "isr_noerr 16",
+    "isr_err   17",
+    "isr_noerr 18",
+    "isr_noerr 19",
+    "isr_noerr 20",
+    "isr_err   21",
+    "isr_noerr 22",
+    "isr_noerr 23",
+    "isr_noerr 24",
+    "isr_noerr 25",
+    "isr_noerr 26",
+    "isr_noerr 27",
+    "isr_noerr 28",
+    "isr_err   29",
+    "isr_err   30",
+    "isr_noerr 31",
+    ".irp vec, 32,33,34,35,36,37,38,39,40,41,42,43,44,45,46,47",
+    "    isr_noerr \\vec",
+    ".endr",
+    "",
+    ".global isr_stub_128",
+    "isr_noerr 128",
+    "",
+    "isr_common:",
+    "    push rax",
+    "    push rbx",
+    "    push rcx",
+    "    push rdx",
+    "    push rsi",
+    "    push rdi",
+    "    push rbp",
+    "    push r8",
+    "    push r9",
+    "    push r10",
+    "    push r11",
+    "    push r12",
+    "    push r13",
+    "    push r14",
+    "    push r15",
+    "    mov rdi, rsp",
+    "    cld",
+    "    call {dispatch}",
+    "    pop r15",
+    "    pop r14",
+    "    pop r13",
+    "    pop r12",
+    "    pop r11",
+    "    pop r10",
+    "    pop r9",
+    "    pop r8",
+    "    pop rbp",
+    "    pop rdi",
+    "    pop rsi",
+    "    pop rdx",
+    "    pop rcx",
+    "    pop rbx",
+    "    pop rax",
+    // Quitar vector y código de error
+    "    add rsp, 16",
+    "    iretq",
+    "",
+    // En .data.rel.ro y no en .rodata: el kernel es PIE y la tabla necesita relocaciones
+    ".pushsection .data.rel.ro, \"aw\"",
+    ".balign 8",
+    ".global isr_stub_table",
+    "isr_stub_table:",
+    ".irp vec, 0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32,33,34,35,36,37,38,39,40,41,42,43,44,45,46,47",
+    "    .quad isr_stub_\\vec",
+    ".endr",
+    ".popsection",
+    dispatch = sym interrupt_dispatch,
+);
@@ -0,0 +1,39 @@
+//! PIT como reloj del sistema y fuente de la expropiación
+
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::instructions::port::Port;
+use super::pic;
+
+pub const TICKS_PER_SECOND: u64 = 100;
+
+const PIT_FREQUENCY: u64 = 1_193_182;
+const PIT_CHANNEL0: u16 = 0x40;
+const PIT_COMMAND: u16 = 0x43;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// Programa el canal 0 del PIT en modo onda cuadrada
+pub fn init() {
+    let divisor = (PIT_FREQUENCY / TICKS_PER_SECOND) as u16;
+    unsafe {
+        Port::<u8>::new(PIT_COMMAND).write(0x36);
+        let mut channel0: Port<u8> = Port::new(PIT_CHANNEL0);
+        channel0.write(divisor as u8);
+        channel0.write((divisor >> 8) as u8);
+    }
+}
+
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+pub fn ms_to_ticks(ms: u64) -> u64 {
+    (ms * TICKS_PER_SECOND).div_ceil(1000)
+}
+
+pub(super) fn handle() {
+    TICKS.fetch_add(1, Ordering::Relaxed);
+    // EOI antes de planificar: puede que no volvamos aquí en un rato
+    pic::end_of_interrupt(0);
+    crate::task::on_tick();
+}
@@ -1,10 +1,22 @@
 use x86_64::instructions::port::Port;
 use crate::framebuffer::{WRITER, INPUT_PROMPT};
+use crate::sync::IrqMutex;
+use crate::task::{self, TaskId};
 use spin::Mutex;
+use alloc::collections::VecDeque;
+use alloc::string::String;
 
 // Buffer para la línea actual
-static INPUT_BUFFER: Mutex<[u8; 256]> = Mutex::new([0; 256]);
-static INPUT_LEN: Mutex<usize> = Mutex::new(0);
+static INPUT_BUFFER: Mutex<String> = Mutex::new(String::new());
+
+// Líneas terminadas (con su '\n') y tareas esperando a que llegue una. Cada
+// línea se la lleva un solo lector: la shell o el programa que lea de stdin.
+struct Lines {
+    ready: VecDeque<String>,
+    readers: VecDeque<TaskId>,
+}
+
+static LINES: IrqMutex<Lines> = IrqMutex::new(Lines { ready: VecDeque::new(), readers: VecDeque::new() });
 
 // Mapa de scancodes a ASCII (scancode set 1)
 fn scancode_to_ascii(sc: u8, shift: bool) -> Option<char> {
@@ -105,7 +117,7 @@ pub fn init() {
     }
 }
 
-// Procesar teclas (llamar desde el bucle principal)
+// Procesar teclas (llamado desde la IRQ 1)
 pub fn poll_keyboard() {
     if !key_available() {
         return;
@@ -125,51 +137,31 @@ pub fn poll_keyboard() {
 }
 
 fn handle_character(c: char) {
+    if c == '\n' {
+        let mut line = core::mem::take(&mut *INPUT_BUFFER.lock());
+        line.push('\n');
+        crate::println!("");
+
+        let mut lines = LINES.lock();
+        lines.ready.push_back(line);
+        if let Some(reader) = lines.readers.pop_front() {
+            task::wake(reader);
+        }
+        return;
+    }
+
     let mut writer = WRITER.lock();
 
     match c {
-        '\n' => {
-            if let Some(fb) = writer.as_mut() {
-                unsafe {
-                    fb.print("\n");
-
-                    // Mostrar lo que se escribió
-                    let buffer = INPUT_BUFFER.lock();
-                    let len = *INPUT_LEN.lock();
-                    fb.print("Has escrito: ");
-                    if len > 0 {
-                        let mut temp = [0u8; 256];
-                        for i in 0..len {
-                            temp[i] = buffer[i];
-                        }
-                        let s = core::str::from_utf8(&temp[..len]).unwrap_or("");
-                        fb.print(s);
-                    }
-                    fb.print("\n");
-
-                    // Limpiar buffer
-                    *INPUT_LEN.lock() = 0;
-                    fb.print(INPUT_PROMPT);
-                }
-            }
-        }
         '\x08' => { // Backspace
             if let Some(fb) = writer.as_mut() {
                 unsafe {
-                    let mut len = INPUT_LEN.lock();
-                    if *len > 0 {
-                        *len -= 1;
-
+                    let mut buffer = INPUT_BUFFER.lock();
+                    if buffer.pop().is_some() {
                         // Volver a mostrar la línea
                         fb.print("\r");
                         fb.print(INPUT_PROMPT);
-                        let buffer = INPUT_BUFFER.lock();
-                        let mut temp = [0u8; 256];
-                        for i in 0..*len {
-                            temp[i] = buffer[i];
-                        }
-                        let s = core::str::from_utf8(&temp[..*len]).unwrap_or("");
-                        fb.print(s);
+                        fb.print(&buffer);
                         fb.print(" ");
                     }
                 }
@@ -178,20 +170,55 @@ fn handle_character(c: char) {
         c if c.is_ascii_graphic() || c == ' ' => {
             if let Some(fb) = writer.as_mut() {
                 unsafe {
-                    let mut len = INPUT_LEN.lock();
-                    let mut buffer = INPUT_BUFFER.lock();
-                    if *len < 255 {
-                        buffer[*len] = c as u8;
-                        *len += 1;
-
-                        let mut temp = [0u8; 1];
-                        temp[0] = c as u8;
-                        let s = core::str::from_utf8(&temp).unwrap_or("");
-                        fb.print(s);
-                    }
+                    INPUT_BUFFER.lock().push(c);
+
+                    let mut temp = [0u8; 4];
+                    fb.print(c.encode_utf8(&mut temp));
                 }
             }
         }
         _ => {}
     }
 }
+
+/// Espera a que haya una línea y copia en `buf` lo que quepa (el resto queda
+/// para la siguiente lectura). Devuelve los bytes copiados.
+pub fn read(buf: &mut [u8]) -> usize {
+    if buf.is_empty() {
+        return 0;
+    }
+    loop {
+        {
+            let mut lines = LINES.lock();
+            if let Some(line) = lines.ready.front_mut() {
+                let count = line.len().min(buf.len());
+                buf[..count].copy_from_slice(&line.as_bytes()[..count]);
+                if count == line.len() {
+                    lines.ready.pop_front();
+                } else {
+                    line.drain(..count);
+                }
+                return count;
+            }
+            // Con LINES cogido: si la línea llega antes de ceder el CPU, el
+            // `wake` nos vuelve a poner en la cola
+            lines.readers.push_back(task::current_id());
+            task::mark_blocked();
+        }
+        task::yield_now();
+    }
+}
+
+/// Espera a una línea completa y la devuelve sin el '\n'
+pub fn read_line() -> String {
+    let mut line = String::new();
+    let mut buf = [0u8; 64];
+    loop {
+        let count = read(&mut buf);
+        line.push_str(core::str::from_utf8(&buf[..count]).unwrap_or(""));
+        if line.ends_with('\n') {
+            line.pop();
+            return line;
+        }
+    }
+}
@@ -1,20 +1,32 @@
 #![no_std]
 #![no_main]
+#![feature(alloc_error_handler)]
+
+extern crate alloc;
 
 mod font;
 mod framebuffer;
+mod gdt;
+mod interrupts;
 mod keyboard;
 mod elf;
 mod syscall;
 mod memory;
+mod usermode;
+mod sync;
+mod task;
+mod process;
+mod shell;
+mod programs;
+mod file;
 
 use framebuffer::{Framebuffer, WRITER, INPUT_PROMPT};
-use limine::request::{FramebufferRequest, MemoryMapRequest, HhdmRequest};
+use limine::request::{FramebufferRequest, MemoryMapRequest, HhdmRequest, ModuleRequest};
 use limine::memory_map::EntryType;
+use core::alloc::Layout;
 use core::panic::PanicInfo;
-use spin::Mutex;
-
-static HELLO_ELF: &[u8] = include_bytes!("user/hello.elf");
+use process::ExitStatus;
+use sync::IrqMutex;
 
 #[used]
 #[link_section = ".requests"]
@@ -32,6 +44,10 @@ static MEMORY_MAP_REQUEST: MemoryMapRequest = MemoryMapRequest::new();
 #[link_section = ".requests"]
 static HHDM_REQUEST: HhdmRequest = HhdmRequest::new();
 
+#[used]
+#[link_section = ".requests"]
+static MODULE_REQUEST: ModuleRequest = ModuleRequest::new();
+
 #[used]
 #[link_section = ".requests_start_marker"]
 static _START_MARKER: u64 = 0;
@@ -40,8 +56,11 @@ static _START_MARKER: u64 = 0;
 #[link_section = ".requests_end_marker"]
 static _END_MARKER: u64 = 0;
 
+/// Programa (módulo de Limine) que se lanza al arrancar
+const BOOT_PROGRAM: &str = "hello";
+
 // Offset HHDM global
-pub static HHDM_OFFSET: Mutex<Option<u64>> = Mutex::new(None);
+pub static HHDM_OFFSET: IrqMutex<Option<u64>> = IrqMutex::new(None);
 
 // Función para convertir física a virtual
 pub fn phys_to_virt(phys: u64) -> u64 {
@@ -67,6 +86,11 @@ extern "C" fn _start() -> ! {
     // Inicializar teclado
     keyboard::init();
     
+    // GDT propia (con TSS), IDT y SYSCALL/SYSRET
+    gdt::init();
+    interrupts::init();
+    syscall::init();
+    
     // Obtener HHDM offset
     let hhdm_response = HHDM_REQUEST.get_response().expect("No se pudo obtener HHDM");
     *HHDM_OFFSET.lock() = Some(hhdm_response.offset());
@@ -77,6 +101,13 @@ extern "C" fn _start() -> ! {
     // Inicializar frame allocator
     memory::FRAME_ALLOCATOR.lock().init(memory_map_response);
     
+    // NXE y PML4 del kernel, luego el heap (a partir de aquí se puede usar `alloc`)
+    memory::init_kernel_space();
+    memory::init_heap().expect("No se pudo inicializar el heap");
+    
+    // Programas de usuario (módulos de Limine)
+    programs::init(MODULE_REQUEST.get_response());
+    
     // Mostrar memory map (debug)
     println!("=== Memory Map ===");
     for entry in memory_map_response.entries() {
@@ -96,45 +127,76 @@ extern "C" fn _start() -> ! {
             base, base + len, kind, len / 1024);
     }
     println!("==================");
+    {
+        let allocator = memory::FRAME_ALLOCATOR.lock();
+        println!("Frames: {} usados, {} libres, {} en total",
+            allocator.used_frames(), allocator.free_frames(), allocator.total_frames());
+    }
     
     println!("========================================");
     println!("   DUCKOS - Ejecutando programa ELF    ");
     println!("========================================");
     println!("");
     
-    // Crear syscalls
-    let mut syscalls = syscall::KernelSyscalls::new();
+    // Planificador: este contexto pasa a ser la tarea "kernel" (la shell)
+    task::init();
+    process::init();
+    x86_64::instructions::interrupts::enable();
     
-    // Cargar y ejecutar programa
-    println!("Cargando programa hello.elf...");
-    println!("Tamaño del ELF: {} bytes", HELLO_ELF.len());
+    println!("Módulos:");
+    for module in programs::modules() {
+        println!("  {} ({}, {} bytes)", module.name(), module.path, module.data.len());
+    }
     
-    match elf::ElfLoader::load_and_execute(HELLO_ELF, &mut syscalls) {
-        Ok(()) => {
-            println!("✅ Programa ejecutado correctamente");
-        }
-        Err(e) => {
-            println!("❌ Error al ejecutar programa: {}", e);
+    // Cargar y ejecutar programa en su propio proceso
+    match programs::find(BOOT_PROGRAM) {
+        Some((name, file)) => {
+            println!("Cargando programa {}...", name);
+            println!("Tamaño del ELF: {} bytes", file.len());
+            
+            let pid = process::spawn_elf(name, file, alloc::vec![name.into()]);
+            match process::waitpid(Some(pid)) {
+                Ok((_, ExitStatus::Code(0))) => {
+                    println!("✅ Programa ejecutado correctamente");
+                }
+                Ok((_, status)) => {
+                    println!("⚠️  Programa terminado con {}", status);
+                }
+                Err(e) => {
+                    println!("❌ Error al esperar al programa: {}", e);
+                }
+            }
         }
+        None => println!("⚠️  No hay módulo {}: revisa limine.conf", BOOT_PROGRAM),
     }
     
     println!("");
     println!("Volviendo al kernel...");
     println!("");
-    print!("{}", INPUT_PROMPT);
     
+    // Las teclas llegan por la IRQ 1; la shell duerme hasta que hay una línea
     loop {
-        keyboard::poll_keyboard();
-        for _ in 0..100000 {
-            core::hint::spin_loop();
-        }
+        print!("{}", INPUT_PROMPT);
+        let line = keyboard::read_line();
+        shell::execute(&line);
     }
 }
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    x86_64::instructions::interrupts::disable();
     println!("💥 KERNEL PANIC: {}", info);
     loop {
         x86_64::instructions::hlt();
     }
 }
+
+#[alloc_error_handler]
+fn alloc_error(layout: Layout) -> ! {
+    let (used, size) = {
+        let heap = memory::ALLOCATOR.lock();
+        (heap.used(), heap.size())
+    };
+    panic!("Sin memoria en el heap: pedidos {} bytes (alineación {}), {} de {} bytes usados",
+        layout.size(), layout.align(), used, size);
+}
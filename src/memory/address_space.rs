@@ -0,0 +1,576 @@
+// src/memory/address_space.rs
+//! Espacios de direcciones por programa
+//!
+//! Cada `AddressSpace` tiene su propia PML4. La mitad alta (entradas 256..512:
+//! HHDM, heap y kernel) se copia de la PML4 del kernel, así que las tablas de
+//! niveles inferiores del kernel se comparten; la mitad baja es del programa
+//! y se libera entera al destruirlo.
+//!
+//! `fork` no copia la memoria: los dos espacios comparten los frames en
+//! solo lectura, marcados con `COPY_ON_WRITE`, y el primero que escribe en
+//! una página se lleva una copia (ver `break_cow`).
+
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use x86_64::structures::paging::{FrameAllocator, FrameDeallocator, PageTableFlags, PhysFrame};
+use x86_64::registers::control::{Cr0, Cr0Flags, Cr3};
+use x86_64::instructions::tlb;
+use x86_64::registers::model_specific::{Efer, EferFlags};
+use x86_64::VirtAddr;
+use crate::sync::IrqMutex;
+use super::FRAME_ALLOCATOR;
+use super::paging::{self, table_at, KERNEL_HALF};
+
+static KERNEL_PML4: IrqMutex<Option<PhysFrame>> = IrqMutex::new(None);
+
+/// Activa NXE y guarda la PML4 actual (la de Limine) como la del kernel.
+///
+/// Ojo: las entradas de la PML4 de la mitad alta que se creen después de
+/// clonar un espacio no llegan a él, así que el heap y demás regiones del
+/// kernel tienen que mapearse antes de cargar programas.
+pub fn init_kernel_space() {
+    // Sin NXE el bit NO_EXECUTE es reservado y usarlo provoca un fallo
+    unsafe { Efer::update(|flags| flags.insert(EferFlags::NO_EXECUTE_ENABLE)) };
+    // Con WP el kernel también respeta las páginas de solo lectura, así que
+    // sus escrituras en páginas copy-on-write pasan por el page fault
+    unsafe { Cr0::update(|flags| flags.insert(Cr0Flags::WRITE_PROTECT)) };
+
+    let (frame, _) = Cr3::read();
+    *KERNEL_PML4.lock() = Some(frame);
+}
+
+/// Bit libre de las entradas de página: escribible, pero compartida con otro
+/// espacio hasta que alguien escriba en ella
+pub const COPY_ON_WRITE: PageTableFlags = PageTableFlags::BIT_9;
+
+fn kernel_pml4() -> PhysFrame {
+    KERNEL_PML4.lock().expect("Espacio del kernel no inicializado")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    Bss,
+    Heap,
+    Stack,
+    /// Mapeo creado con `mmap`
+    Mmap,
+}
+
+/// Quién accede a la memoria de un espacio y para qué
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    /// El kernel escribe sin mirar los permisos (al cargar un programa)
+    Kernel,
+    UserRead,
+    UserWrite,
+}
+
+/// Rango de la mitad baja respaldado bajo demanda: sus páginas no se mapean
+/// hasta el primer acceso, y entonces se les da un frame a cero.
+#[derive(Debug, Clone, Copy)]
+pub struct Region {
+    pub start: VirtAddr,
+    pub end: VirtAddr,
+    pub flags: PageTableFlags,
+    pub kind: RegionKind,
+}
+
+impl Region {
+    pub fn contains(&self, addr: VirtAddr) -> bool {
+        self.start <= addr && addr < self.end
+    }
+}
+
+/// Programa break (`brk`): el heap va de `start` a `current`, y la región
+/// `Heap` lo cubre redondeado a página
+#[derive(Debug, Clone, Copy)]
+struct Brk {
+    start: VirtAddr,
+    current: u64,
+}
+
+pub struct AddressSpace {
+    pml4: PhysFrame,
+    regions: Vec<Region>,
+    brk: Option<Brk>,
+    // Los mapeos de `mmap` se reparten hacia abajo desde aquí
+    mmap_next: VirtAddr,
+    // Final de las direcciones de usuario: la mitad baja, o 4 GiB en modo
+    // compatibilidad
+    user_end: u64,
+}
+
+impl AddressSpace {
+    /// Crea un espacio vacío que comparte la mitad alta con el kernel
+    pub fn new() -> Result<Self, &'static str> {
+        let pml4 = FRAME_ALLOCATOR.lock().allocate_frame().ok_or("No hay frames disponibles")?;
+
+        unsafe {
+            let table = table_at(pml4);
+            let kernel = table_at(kernel_pml4());
+            for i in 0..KERNEL_HALF {
+                table[i].set_unused();
+            }
+            for i in KERNEL_HALF..512 {
+                table[i] = kernel[i].clone();
+            }
+        }
+
+        Ok(AddressSpace {
+            pml4,
+            regions: Vec::new(),
+            brk: None,
+            mmap_next: VirtAddr::zero(),
+            user_end: (KERNEL_HALF as u64) << 39,
+        })
+    }
+
+    /// Limita las direcciones de usuario a `[0, end)` (4 GiB para un
+    /// programa de 32 bits): ni `brk` ni `mmap` pasarán de ahí
+    pub fn set_user_end(&mut self, end: u64) {
+        self.user_end = end.min((KERNEL_HALF as u64) << 39);
+    }
+
+    /// Mapea páginas nuevas (a cero) en este espacio, esté activo o no
+    pub fn map_range(
+        &mut self,
+        virt_start: VirtAddr,
+        page_count: u64,
+        flags: PageTableFlags,
+    ) -> Result<(), &'static str> {
+        paging::map_range_in(self.pml4, virt_start, page_count, flags)
+    }
+
+    /// Desmapea páginas, libera sus frames y las tablas que queden vacías
+    pub fn unmap_range(&mut self, virt_start: VirtAddr, page_count: u64) -> Result<(), &'static str> {
+        paging::unmap_range_in(self.pml4, virt_start, page_count)
+    }
+
+    /// Cambia los flags de páginas ya mapeadas
+    pub fn protect_range(
+        &mut self,
+        virt_start: VirtAddr,
+        page_count: u64,
+        flags: PageTableFlags,
+    ) -> Result<(), &'static str> {
+        paging::protect_range_in(self.pml4, virt_start, page_count, flags)
+    }
+
+    /// Flags de la página que contiene `addr`, o `None` si no está mapeada
+    pub fn page_flags(&self, addr: VirtAddr) -> Option<PageTableFlags> {
+        paging::translate_in(self.pml4, addr).map(|(_, flags)| flags)
+    }
+
+    /// Puntero (por el HHDM) al byte `addr` de este espacio, válido hasta el
+    /// final de la página. Las páginas de una región perezosa se respaldan y
+    /// las copy-on-write se separan antes de escribir en ellas.
+    fn page_ptr(&mut self, addr: VirtAddr, access: Access) -> Result<*mut u8, &'static str> {
+        if usize::from(addr.p4_index()) >= KERNEL_HALF {
+            return Err("Dirección de la mitad del kernel");
+        }
+        let write = access != Access::UserRead;
+        let flags = match paging::translate_in(self.pml4, addr) {
+            Some((_, flags)) => flags,
+            None => {
+                self.handle_page_fault(addr, write)?;
+                self.page_flags(addr).ok_or("Página no mapeada")?
+            }
+        };
+
+        if access != Access::Kernel {
+            if !flags.contains(PageTableFlags::USER_ACCESSIBLE) {
+                return Err("Página no accesible desde ring 3");
+            }
+            if write && !flags.intersects(PageTableFlags::WRITABLE | COPY_ON_WRITE) {
+                return Err("Página de solo lectura");
+            }
+        }
+        if write && flags.contains(COPY_ON_WRITE) {
+            self.break_cow(addr.align_down(4096u64))?;
+        }
+
+        let (phys, _) = paging::translate_in(self.pml4, addr).ok_or("Página no mapeada")?;
+        Ok(crate::phys_to_virt(phys.as_u64()) as *mut u8)
+    }
+
+    /// Recorre `len` bytes desde `addr` página a página, pasando a `f` el
+    /// puntero de cada trozo, su posición dentro del rango y su tamaño
+    fn for_each_chunk(
+        &mut self,
+        addr: VirtAddr,
+        len: usize,
+        access: Access,
+        mut f: impl FnMut(*mut u8, usize, usize),
+    ) -> Result<(), &'static str> {
+        if addr.as_u64().checked_add(len as u64).is_none_or(|end| end > self.user_end) {
+            return Err("Rango fuera de la mitad baja");
+        }
+
+        let mut done = 0;
+        while done < len {
+            let current = addr + done as u64;
+            let chunk = (4096 - usize::from(current.page_offset())).min(len - done);
+            let ptr = self.page_ptr(current, access)?;
+            f(ptr, done, chunk);
+            done += chunk;
+        }
+        Ok(())
+    }
+
+    /// Copia `data` a `addr` sin necesidad de que este espacio esté activo
+    /// (ignora los permisos de las páginas)
+    pub fn write(&mut self, addr: VirtAddr, data: &[u8]) -> Result<(), &'static str> {
+        self.for_each_chunk(addr, data.len(), Access::Kernel, |dest, done, chunk| unsafe {
+            core::ptr::copy_nonoverlapping(data[done..].as_ptr(), dest, chunk)
+        })
+    }
+
+    /// Pone a cero `len` bytes desde `addr` (como `write`)
+    pub fn zero(&mut self, addr: VirtAddr, len: usize) -> Result<(), &'static str> {
+        self.for_each_chunk(addr, len, Access::Kernel, |dest, _, chunk| unsafe {
+            core::ptr::write_bytes(dest, 0, chunk)
+        })
+    }
+
+    /// Lee memoria del programa respetando sus permisos: falla si algún byte
+    /// no es accesible desde ring 3 (o no puede respaldarse)
+    pub fn copy_from_user(&mut self, src: VirtAddr, buf: &mut [u8]) -> Result<(), &'static str> {
+        self.for_each_chunk(src, buf.len(), Access::UserRead, |ptr, done, chunk| unsafe {
+            core::ptr::copy_nonoverlapping(ptr, buf[done..].as_mut_ptr(), chunk)
+        })
+    }
+
+    /// Escribe en memoria del programa respetando sus permisos
+    pub fn copy_to_user(&mut self, dst: VirtAddr, data: &[u8]) -> Result<(), &'static str> {
+        self.for_each_chunk(dst, data.len(), Access::UserWrite, |ptr, done, chunk| unsafe {
+            core::ptr::copy_nonoverlapping(data[done..].as_ptr(), ptr, chunk)
+        })
+    }
+
+    /// Registra una región respaldada bajo demanda (`start` y `end` alineados a página)
+    pub fn add_lazy_region(&mut self, start: VirtAddr, end: VirtAddr, flags: PageTableFlags, kind: RegionKind) {
+        if start < end {
+            self.regions.push(Region { start, end, flags, kind });
+        }
+    }
+
+    /// Lo primero que ocupa `[start, end)`: el inicio de una región que se
+    /// solapa con él (aunque empiece antes) o la primera página mapeada.
+    /// Solo se miran las regiones y las tablas de páginas que existen, nunca
+    /// el rango página a página.
+    fn first_used(&self, start: VirtAddr, end: VirtAddr) -> Option<VirtAddr> {
+        let region = self.regions.iter()
+            .filter(|region| region.start < end && start < region.end)
+            .map(|region| region.start)
+            .min();
+        let mapped = paging::first_mapped_in(self.pml4, start, end);
+        region.into_iter().chain(mapped).min()
+    }
+
+    /// Quita `[start, end)` (alineados a página) de las regiones perezosas,
+    /// partiéndolas si hace falta
+    fn remove_regions(&mut self, start: VirtAddr, end: VirtAddr) {
+        let mut kept = Vec::with_capacity(self.regions.len() + 1);
+        for region in self.regions.drain(..) {
+            if region.end <= start || end <= region.start {
+                kept.push(region);
+                continue;
+            }
+            if region.start < start {
+                kept.push(Region { end: start, ..region });
+            }
+            if end < region.end {
+                kept.push(Region { start: end, ..region });
+            }
+        }
+        self.regions = kept;
+    }
+
+    /// Empieza el heap del programa (`brk`) en `start`, justo después de su imagen
+    pub fn init_brk(&mut self, start: VirtAddr) {
+        let start = start.align_up(4096u64);
+        self.brk = Some(Brk { start, current: start.as_u64() });
+    }
+
+    /// Mueve el final del heap a `new` si se puede y devuelve el final
+    /// resultante (como `brk` de Linux, que no falla: devuelve el anterior)
+    pub fn set_brk(&mut self, new: u64) -> u64 {
+        let Some(brk) = self.brk else { return 0 };
+        let user_end = self.user_end;
+        // El final redondeado tiene que seguir siendo una dirección canónica
+        if new < brk.start.as_u64() || new >= user_end - 4096 {
+            return brk.current;
+        }
+
+        let old_end = VirtAddr::new(brk.current).align_up(4096u64);
+        let new_end = VirtAddr::new(new).align_up(4096u64);
+        match new_end.cmp(&old_end) {
+            Ordering::Greater => {
+                // Por encima de `mmap_next` están los mapeos y la pila
+                if new_end > self.mmap_next || self.first_used(old_end, new_end).is_some() {
+                    return brk.current;
+                }
+            }
+            Ordering::Less => {
+                self.remove_regions(new_end, old_end);
+                let pages = (old_end - new_end) / 4096;
+                if self.unmap_range(new_end, pages).is_err() {
+                    return brk.current;
+                }
+            }
+            Ordering::Equal => {}
+        }
+
+        self.regions.retain(|region| region.kind != RegionKind::Heap);
+        let flags = PageTableFlags::PRESENT
+            | PageTableFlags::WRITABLE
+            | PageTableFlags::USER_ACCESSIBLE
+            | PageTableFlags::NO_EXECUTE;
+        self.add_lazy_region(brk.start, new_end, flags, RegionKind::Heap);
+        self.brk = Some(Brk { current: new, ..brk });
+        new
+    }
+
+    /// Los mapeos anónimos se colocarán por debajo de `top`
+    pub fn set_mmap_top(&mut self, top: VirtAddr) {
+        self.mmap_next = top.align_down(4096u64);
+    }
+
+    /// Reserva `len` bytes anónimos (a cero, respaldados bajo demanda). Con
+    /// `fixed` se colocan justo ahí, sustituyendo lo que hubiera.
+    pub fn map_anonymous(
+        &mut self,
+        fixed: Option<VirtAddr>,
+        len: u64,
+        flags: PageTableFlags,
+    ) -> Result<VirtAddr, &'static str> {
+        let len = len.checked_add(4095).ok_or("Tamaño no válido")? & !4095;
+        if len == 0 {
+            return Err("Tamaño no válido");
+        }
+        let user_end = self.user_end;
+
+        let start = match fixed {
+            Some(start) => {
+                // La página 0 se queda sin mapear, como en el caso sin dirección
+                if !start.is_aligned(4096u64)
+                    || start.as_u64() < 4096
+                    || start.as_u64().checked_add(len).is_none_or(|end| end >= user_end)
+                {
+                    return Err("Dirección no válida");
+                }
+                self.unmap_user(start, len)?;
+                start
+            }
+            None => {
+                // Hacia abajo, saltando por debajo de lo que ya esté ocupado
+                let mut end = self.mmap_next;
+                loop {
+                    let start = end.as_u64().checked_sub(len)
+                        .filter(|&start| start >= 4096)
+                        .ok_or("Sin espacio de direcciones")?;
+                    let start = VirtAddr::new(start);
+                    match self.first_used(start, end) {
+                        Some(used) => end = used,
+                        None => {
+                            self.mmap_next = start;
+                            break start;
+                        }
+                    }
+                }
+            }
+        };
+
+        self.add_lazy_region(start, start + len, flags, RegionKind::Mmap);
+        Ok(start)
+    }
+
+    /// Desmapea `[start, start + len)` (páginas y regiones perezosas)
+    pub fn unmap_user(&mut self, start: VirtAddr, len: u64) -> Result<(), &'static str> {
+        let user_end = self.user_end;
+        if !start.is_aligned(4096u64) {
+            return Err("Dirección no alineada");
+        }
+        let end = start.as_u64().checked_add(len).and_then(|end| end.checked_add(4095))
+            .map(|end| end & !4095)
+            .filter(|&end| end < user_end)
+            .ok_or("Rango fuera de la mitad baja")?;
+        let end = VirtAddr::new(end);
+        self.remove_regions(start, end);
+        self.unmap_range(start, (end - start) / 4096)
+    }
+
+    /// Intenta resolver un fallo de página en `addr`: una página no presente
+    /// de una región perezosa recibe un frame nuevo, y una escritura en una
+    /// página copy-on-write, su propia copia.
+    pub fn handle_page_fault(&mut self, addr: VirtAddr, write: bool) -> Result<(), &'static str> {
+        let page = addr.align_down(4096u64);
+        if let Some(flags) = self.page_flags(page) {
+            if write && flags.contains(COPY_ON_WRITE) {
+                return self.break_cow(page);
+            }
+            return Err("Violación de permisos");
+        }
+
+        let region = *self.regions.iter()
+            .find(|region| region.contains(addr))
+            .ok_or("Dirección fuera de cualquier región")?;
+        self.map_range(page, 1, region.flags)
+    }
+
+    /// Hace escribible una página copy-on-write; si el frame sigue
+    /// compartido, la página pasa a apuntar a una copia.
+    fn break_cow(&mut self, page: VirtAddr) -> Result<(), &'static str> {
+        let (phys, flags) = paging::translate_in(self.pml4, page).ok_or("Página no mapeada")?;
+        let frame = PhysFrame::containing_address(phys);
+        let flags = (flags - COPY_ON_WRITE) | PageTableFlags::WRITABLE;
+
+        let copy = {
+            let mut allocator = FRAME_ALLOCATOR.lock();
+            if !allocator.is_shared(frame) {
+                None
+            } else {
+                Some(allocator.allocate_frame().ok_or("No hay frames disponibles")?)
+            }
+        };
+        let Some(copy) = copy else {
+            // Ya es el único dueño (el otro espacio la copió o terminó)
+            return self.protect_range(page, 1, flags);
+        };
+
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                crate::phys_to_virt(frame.start_address().as_u64()) as *const u8,
+                crate::phys_to_virt(copy.start_address().as_u64()) as *mut u8,
+                4096,
+            );
+        }
+        let old = paging::replace_frame_in(self.pml4, page, copy, flags)?;
+        unsafe { FRAME_ALLOCATOR.lock().release(old) };
+        Ok(())
+    }
+
+    /// Duplica este espacio para un proceso hijo. Las páginas escribibles
+    /// pasan a ser copy-on-write en los dos; el resto se comparte tal cual.
+    pub fn fork(&mut self) -> Result<AddressSpace, &'static str> {
+        let mut child = AddressSpace::new()?;
+        child.regions = self.regions.clone();
+        child.brk = self.brk;
+        child.mmap_next = self.mmap_next;
+        child.user_end = self.user_end;
+
+        {
+            let mut allocator = FRAME_ALLOCATOR.lock();
+            unsafe { Self::share_table(&mut allocator, self.pml4, child.pml4, 4)? };
+        }
+
+        // Las páginas del padre que eran escribibles ya no lo son
+        if self.is_active() {
+            tlb::flush_all();
+        }
+        Ok(child)
+    }
+
+    // Copia en `copy` (ya enlazada y vacía) la mitad baja de la tabla
+    // `frame`, compartiendo los frames de las páginas. Si falta memoria a
+    // medias, lo ya copiado lo libera el Drop del hijo.
+    unsafe fn share_table(
+        allocator: &mut super::BitmapFrameAllocator,
+        frame: PhysFrame,
+        copy: PhysFrame,
+        level: u8,
+    ) -> Result<(), &'static str> {
+        let table = table_at(frame);
+        let copy_table = table_at(copy);
+        // En la PML4, la mitad alta ya es la del kernel
+        let entries = if level == 4 { KERNEL_HALF } else { 512 };
+
+        for (entry, copy_entry) in table.iter_mut().zip(copy_table.iter_mut()).take(entries) {
+            let flags = entry.flags();
+            if !flags.contains(PageTableFlags::PRESENT) {
+                continue;
+            }
+            let target = PhysFrame::containing_address(entry.addr());
+            if level > 1 {
+                let child = allocator.allocate_frame().ok_or("No hay frames disponibles")?;
+                for child_entry in table_at(child).iter_mut() {
+                    child_entry.set_unused();
+                }
+                copy_entry.set_frame(child, flags);
+                Self::share_table(allocator, target, child, level - 1)?;
+            } else {
+                let flags = if flags.contains(PageTableFlags::WRITABLE) {
+                    (flags - PageTableFlags::WRITABLE) | COPY_ON_WRITE
+                } else {
+                    flags
+                };
+                entry.set_flags(flags);
+                copy_entry.set_frame(target, flags);
+                allocator.share(target);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn pml4(&self) -> PhysFrame {
+        self.pml4
+    }
+
+    /// Carga `pml4` en CR3, o la del kernel si es `None`. Quien la pase tiene
+    /// que asegurarse de que su `AddressSpace` sigue vivo.
+    pub fn activate_table(pml4: Option<PhysFrame>) {
+        let target = pml4.unwrap_or_else(kernel_pml4);
+        let (current, flags) = Cr3::read();
+        if current != target {
+            unsafe { Cr3::write(target, flags) };
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        Cr3::read().0 == self.pml4
+    }
+
+    /// Vuelve a la PML4 del kernel
+    pub fn activate_kernel() {
+        Self::activate_table(None);
+    }
+
+    // Libera recursivamente una tabla de la mitad baja y todo lo que cuelga de ella
+    unsafe fn free_table(allocator: &mut super::BitmapFrameAllocator, frame: PhysFrame, level: u8) {
+        let table = table_at(frame);
+        for entry in table.iter_mut() {
+            let flags = entry.flags();
+            if !flags.contains(PageTableFlags::PRESENT) {
+                continue;
+            }
+            // Nunca creamos páginas grandes en la mitad baja
+            if level > 1 && !flags.contains(PageTableFlags::HUGE_PAGE) {
+                Self::free_table(allocator, PhysFrame::containing_address(entry.addr()), level - 1);
+            } else if level == 1 {
+                allocator.release(PhysFrame::containing_address(entry.addr()));
+            }
+            entry.set_unused();
+        }
+        allocator.deallocate_frame(frame);
+    }
+}
+
+impl Drop for AddressSpace {
+    fn drop(&mut self) {
+        if self.is_active() {
+            Self::activate_kernel();
+        }
+
+        let mut allocator = FRAME_ALLOCATOR.lock();
+        unsafe {
+            let table = table_at(self.pml4);
+            for i in 0..KERNEL_HALF {
+                if table[i].flags().contains(PageTableFlags::PRESENT) {
+                    Self::free_table(&mut allocator, PhysFrame::containing_address(table[i].addr()), 3);
+                    table[i].set_unused();
+                }
+            }
+            allocator.deallocate_frame(self.pml4);
+        }
+    }
+}
@@ -0,0 +1,238 @@
+// src/memory/frame_allocator.rs
+//! Allocator de frames físicos con bitmap (un bit por frame de 4 KiB)
+//!
+//! El bitmap cubre desde la región USABLE (o BOOTLOADER_RECLAIMABLE) más
+//! baja hasta la más alta; los huecos entre regiones quedan marcados como
+//! ocupados para siempre. El propio bitmap vive en la primera región USABLE
+//! que tenga sitio, accedido a través del HHDM.
+//!
+//! Las regiones BOOTLOADER_RECLAIMABLE no se liberan nunca: ahí siguen las
+//! tablas de páginas de Limine (las del kernel) y la pila de arranque, que
+//! es la de la shell.
+
+use alloc::collections::BTreeMap;
+use x86_64::structures::paging::{PhysFrame, FrameAllocator, FrameDeallocator, Size4KiB};
+use x86_64::PhysAddr;
+use limine::response::MemoryMapResponse;
+use limine::memory_map::EntryType;
+
+const FRAME_SIZE: u64 = 4096;
+
+pub struct BitmapFrameAllocator {
+    bitmap: *mut u64,
+    words: usize,
+    base: u64,
+    frame_count: usize,
+    total: usize,
+    used: usize,
+    // Primer word donde puede haber un frame libre
+    next_hint: usize,
+    // Referencias extra de los frames compartidos por copy-on-write
+    // (un frame que no está aquí tiene un único dueño)
+    shared: BTreeMap<u64, usize>,
+}
+
+// El bitmap solo se toca con el Mutex de FRAME_ALLOCATOR cogido
+unsafe impl Send for BitmapFrameAllocator {}
+
+impl BitmapFrameAllocator {
+    pub const fn new() -> Self {
+        BitmapFrameAllocator {
+            bitmap: core::ptr::null_mut(),
+            words: 0,
+            base: 0,
+            frame_count: 0,
+            total: 0,
+            used: 0,
+            next_hint: 0,
+            shared: BTreeMap::new(),
+        }
+    }
+
+    pub fn init(&mut self, memory_map: &MemoryMapResponse) {
+        let tracked = |kind: EntryType| {
+            kind == EntryType::USABLE || kind == EntryType::BOOTLOADER_RECLAIMABLE
+        };
+
+        let mut start = u64::MAX;
+        let mut end = 0;
+        for entry in memory_map.entries() {
+            if tracked(entry.entry_type) {
+                start = start.min(entry.base);
+                end = end.max(entry.base + entry.length);
+            }
+        }
+        if start >= end {
+            panic!("No hay memoria USABLE en el memory map");
+        }
+
+        self.base = start & !(FRAME_SIZE - 1);
+        self.frame_count = (end - self.base).div_ceil(FRAME_SIZE) as usize;
+        self.words = self.frame_count.div_ceil(64);
+
+        // Buscar sitio para el bitmap
+        let bitmap_bytes = (self.words * 8) as u64;
+        let bitmap_frames = bitmap_bytes.div_ceil(FRAME_SIZE);
+        let bitmap_phys = memory_map.entries().iter()
+            .find(|entry| entry.entry_type == EntryType::USABLE
+                && entry.length >= bitmap_frames * FRAME_SIZE)
+            .map(|entry| entry.base)
+            .expect("No hay sitio para el bitmap de frames");
+        self.bitmap = crate::phys_to_virt(bitmap_phys) as *mut u64;
+
+        // Todo ocupado salvo lo que el memory map dice que está libre
+        unsafe { core::ptr::write_bytes(self.bitmap, 0xFF, self.words) };
+        self.used = self.frame_count;
+        self.total = 0;
+        for entry in memory_map.entries() {
+            if entry.entry_type == EntryType::USABLE {
+                self.release_range(entry.base, entry.length);
+            }
+        }
+
+        for i in 0..bitmap_frames {
+            self.mark_used(self.index_of(bitmap_phys + i * FRAME_SIZE));
+        }
+        self.next_hint = 0;
+
+        crate::println!("FrameAllocator: {} frames libres de {} ({} KiB de bitmap en {:#x})",
+            self.free_frames(), self.total_frames(), bitmap_bytes / 1024, bitmap_phys);
+    }
+
+    /// Reserva `count` frames físicamente contiguos y devuelve el primero
+    pub fn allocate_contiguous(&mut self, count: usize) -> Option<PhysFrame> {
+        if count == 0 || count > self.free_frames() {
+            return None;
+        }
+
+        let mut run_start = self.next_hint * 64;
+        let mut run_len = 0;
+        let mut index = run_start;
+        while index < self.frame_count {
+            // Saltar words completos ocupados
+            if index % 64 == 0 && run_len == 0 && unsafe { *self.bitmap.add(index / 64) } == u64::MAX {
+                index += 64;
+                run_start = index;
+                continue;
+            }
+
+            if self.is_used(index) {
+                run_len = 0;
+                run_start = index + 1;
+            } else {
+                run_len += 1;
+                if run_len == count {
+                    for i in run_start..run_start + count {
+                        self.mark_used(i);
+                    }
+                    if count == 1 {
+                        self.next_hint = run_start / 64;
+                    }
+                    return Some(self.frame_at(run_start));
+                }
+            }
+            index += 1;
+        }
+
+        // Puede que haya huecos antes de la pista
+        if self.next_hint != 0 {
+            self.next_hint = 0;
+            return self.allocate_contiguous(count);
+        }
+        None
+    }
+
+    /// Libera `count` frames contiguos empezando en `frame`
+    ///
+    /// # Safety
+    /// Los frames no deben seguir en uso.
+    pub unsafe fn deallocate_contiguous(&mut self, frame: PhysFrame, count: usize) {
+        let first = self.index_of(frame.start_address().as_u64());
+        for index in first..first + count {
+            if !self.is_used(index) {
+                panic!("Doble liberación del frame {:#x}", self.frame_at(index).start_address().as_u64());
+            }
+            self.mark_free(index);
+        }
+        self.next_hint = self.next_hint.min(first / 64);
+    }
+
+    /// Suma un dueño más a `frame` (lo comparte otro espacio de direcciones)
+    pub fn share(&mut self, frame: PhysFrame) {
+        *self.shared.entry(frame.start_address().as_u64()).or_insert(0) += 1;
+    }
+
+    pub fn is_shared(&self, frame: PhysFrame) -> bool {
+        self.shared.contains_key(&frame.start_address().as_u64())
+    }
+
+    /// Quita un dueño a `frame` y lo libera si era el último
+    ///
+    /// # Safety
+    /// Quien lo suelta no debe seguir usándolo.
+    pub unsafe fn release(&mut self, frame: PhysFrame) {
+        let addr = frame.start_address().as_u64();
+        match self.shared.get_mut(&addr) {
+            Some(1) => { self.shared.remove(&addr); }
+            Some(count) => *count -= 1,
+            None => self.deallocate_frame(frame),
+        }
+    }
+
+    pub fn total_frames(&self) -> usize {
+        self.total
+    }
+
+    pub fn used_frames(&self) -> usize {
+        self.total - self.free_frames()
+    }
+
+    pub fn free_frames(&self) -> usize {
+        self.frame_count - self.used
+    }
+
+    fn release_range(&mut self, base: u64, length: u64) {
+        let first = self.index_of(base);
+        let count = (length / FRAME_SIZE) as usize;
+        for index in first..first + count {
+            if self.is_used(index) {
+                self.mark_free(index);
+                self.total += 1;
+            }
+        }
+    }
+
+    fn index_of(&self, phys: u64) -> usize {
+        ((phys - self.base) / FRAME_SIZE) as usize
+    }
+
+    fn frame_at(&self, index: usize) -> PhysFrame {
+        PhysFrame::containing_address(PhysAddr::new(self.base + index as u64 * FRAME_SIZE))
+    }
+
+    fn is_used(&self, index: usize) -> bool {
+        unsafe { *self.bitmap.add(index / 64) & (1 << (index % 64)) != 0 }
+    }
+
+    fn mark_used(&mut self, index: usize) {
+        unsafe { *self.bitmap.add(index / 64) |= 1 << (index % 64) };
+        self.used += 1;
+    }
+
+    fn mark_free(&mut self, index: usize) {
+        unsafe { *self.bitmap.add(index / 64) &= !(1 << (index % 64)) };
+        self.used -= 1;
+    }
+}
+
+unsafe impl FrameAllocator<Size4KiB> for BitmapFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame> {
+        self.allocate_contiguous(1)
+    }
+}
+
+impl FrameDeallocator<Size4KiB> for BitmapFrameAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame) {
+        self.deallocate_contiguous(frame, 1);
+    }
+}
@@ -0,0 +1,180 @@
+// src/memory/heap.rs
+//! Heap del kernel: lista enlazada de bloques libres ordenada por dirección.
+//! Al liberar se fusionan los bloques vecinos para no fragmentar.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::mem::{align_of, size_of};
+use core::ptr;
+use spin::Mutex;
+use x86_64::{VirtAddr, structures::paging::PageTableFlags};
+use super::map_range;
+
+pub const HEAP_START: u64 = 0xffff_c000_0000_0000;
+pub const HEAP_SIZE: u64 = 4 * 1024 * 1024;
+
+struct FreeBlock {
+    size: usize,
+    next: *mut FreeBlock,
+}
+
+pub struct LinkedListAllocator {
+    head: *mut FreeBlock,
+    size: usize,
+    used: usize,
+}
+
+// Solo se usa detrás del Mutex de `LockedHeap`
+unsafe impl Send for LinkedListAllocator {}
+
+impl LinkedListAllocator {
+    pub const fn new() -> Self {
+        LinkedListAllocator {
+            head: ptr::null_mut(),
+            size: 0,
+            used: 0,
+        }
+    }
+
+    /// # Safety
+    /// La región tiene que estar mapeada, sin usar, y llamarse una sola vez.
+    pub unsafe fn init(&mut self, start: usize, size: usize) {
+        self.size = size;
+        self.add_free_region(start, size);
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    // Todo bloque tiene que poder volver a la lista como FreeBlock
+    fn block_layout(layout: Layout) -> (usize, usize) {
+        let size = layout.size().max(size_of::<FreeBlock>());
+        let size = align_up(size, align_of::<FreeBlock>());
+        let align = layout.align().max(align_of::<FreeBlock>());
+        (size, align)
+    }
+
+    unsafe fn allocate(&mut self, layout: Layout) -> *mut u8 {
+        let (size, align) = Self::block_layout(layout);
+
+        let mut prev: *mut *mut FreeBlock = &mut self.head;
+        while !(*prev).is_null() {
+            let block = *prev;
+            let start = block as usize;
+            let end = start + (*block).size;
+
+            // El hueco delante de la reserva tiene que poder ser un bloque libre
+            let mut alloc_start = align_up(start, align);
+            if alloc_start != start && alloc_start - start < size_of::<FreeBlock>() {
+                alloc_start = align_up(start + size_of::<FreeBlock>(), align);
+            }
+
+            if let Some(alloc_end) = alloc_start.checked_add(size) {
+                let back = end.saturating_sub(alloc_end);
+                if alloc_end <= end && (back == 0 || back >= size_of::<FreeBlock>()) {
+                    *prev = (*block).next;
+                    if alloc_start > start {
+                        self.add_free_region(start, alloc_start - start);
+                    }
+                    if back > 0 {
+                        self.add_free_region(alloc_end, back);
+                    }
+                    self.used += size;
+                    return alloc_start as *mut u8;
+                }
+            }
+
+            prev = &mut (*block).next;
+        }
+
+        ptr::null_mut()
+    }
+
+    unsafe fn deallocate(&mut self, ptr: *mut u8, layout: Layout) {
+        let (size, _) = Self::block_layout(layout);
+        self.used -= size;
+        self.add_free_region(ptr as usize, size);
+    }
+
+    unsafe fn add_free_region(&mut self, addr: usize, size: usize) {
+        // Buscar la posición ordenada
+        let mut prev: *mut FreeBlock = ptr::null_mut();
+        let mut next = self.head;
+        while !next.is_null() && (next as usize) < addr {
+            prev = next;
+            next = (*next).next;
+        }
+
+        let block = addr as *mut FreeBlock;
+        block.write(FreeBlock { size, next });
+        if prev.is_null() {
+            self.head = block;
+        } else {
+            (*prev).next = block;
+        }
+
+        // Fusionar con el siguiente
+        if !next.is_null() && addr + size == next as usize {
+            (*block).size += (*next).size;
+            (*block).next = (*next).next;
+        }
+
+        // Fusionar con el anterior
+        if !prev.is_null() && prev as usize + (*prev).size == addr {
+            (*prev).size += (*block).size;
+            (*prev).next = (*block).next;
+        }
+    }
+}
+
+pub struct LockedHeap(Mutex<LinkedListAllocator>);
+
+impl LockedHeap {
+    pub const fn new() -> Self {
+        LockedHeap(Mutex::new(LinkedListAllocator::new()))
+    }
+
+    pub fn lock(&self) -> spin::MutexGuard<'_, LinkedListAllocator> {
+        self.0.lock()
+    }
+}
+
+unsafe impl GlobalAlloc for LockedHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // Sin interrupciones: un manejador que reserve memoria no puede
+        // encontrarse el lock cogido por el código al que interrumpió.
+        x86_64::instructions::interrupts::without_interrupts(|| {
+            self.0.lock().allocate(layout)
+        })
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        x86_64::instructions::interrupts::without_interrupts(|| {
+            self.0.lock().deallocate(ptr, layout)
+        })
+    }
+}
+
+#[global_allocator]
+pub static ALLOCATOR: LockedHeap = LockedHeap::new();
+
+fn align_up(addr: usize, align: usize) -> usize {
+    (addr + align - 1) & !(align - 1)
+}
+
+/// Mapea la región del heap y la entrega al allocator
+pub fn init_heap() -> Result<(), &'static str> {
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+    map_range(VirtAddr::new(HEAP_START), HEAP_SIZE / 4096, flags)
+        .map_err(|_| "Error al mapear el heap")?;
+
+    unsafe {
+        ALLOCATOR.lock().init(HEAP_START as usize, HEAP_SIZE as usize);
+    }
+    crate::println!("Heap: {} KiB en {:#x}", HEAP_SIZE / 1024, HEAP_START);
+    Ok(())
+}
@@ -0,0 +1,26 @@
+// src/memory/mod.rs
+mod address_space;
+mod frame_allocator;
+mod heap;
+mod paging;
+
+use x86_64::structures::paging::PageTableFlags;
+use x86_64::VirtAddr;
+use x86_64::registers::control::Cr3;
+use crate::sync::IrqMutex;
+
+pub use address_space::{AddressSpace, RegionKind, init_kernel_space};
+pub use frame_allocator::BitmapFrameAllocator;
+pub use heap::{init_heap, ALLOCATOR};
+
+pub static FRAME_ALLOCATOR: IrqMutex<BitmapFrameAllocator> = IrqMutex::new(BitmapFrameAllocator::new());
+
+/// Mapea `page_count` páginas nuevas (a cero) en el espacio de direcciones activo
+pub fn map_range(
+    virt_start: VirtAddr,
+    page_count: u64,
+    flags: PageTableFlags,
+) -> Result<(), &'static str> {
+    let (level_4_table, _) = Cr3::read();
+    paging::map_range_in(level_4_table, virt_start, page_count, flags)
+}
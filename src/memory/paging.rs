@@ -0,0 +1,254 @@
+// src/memory/paging.rs
+//! Operaciones sobre tablas de páginas de cualquier PML4 (activa o no)
+//!
+//! Solo hay un CPU, así que la "shootdown" del TLB se reduce a `invlpg`
+//! local, y solo hace falta cuando la PML4 tocada es la que está en CR3:
+//! al cargar otra en CR3 se descartan sus entradas no globales.
+
+use x86_64::structures::paging::{
+    FrameAllocator, FrameDeallocator, Mapper, OffsetPageTable, Page, PageTable,
+    PageTableFlags, PhysFrame, Size4KiB, Translate,
+};
+use x86_64::structures::paging::mapper::{MapperFlush, TranslateResult};
+use x86_64::registers::control::Cr3;
+use x86_64::instructions::tlb;
+use x86_64::{PhysAddr, VirtAddr};
+use super::{BitmapFrameAllocator, FRAME_ALLOCATOR};
+
+// Primera entrada de la PML4 que pertenece al kernel
+pub const KERNEL_HALF: usize = 256;
+
+/// `OffsetPageTable` sobre la PML4 que está en `pml4`, accedida por el HHDM
+///
+/// # Safety
+/// `pml4` tiene que ser una tabla de nivel 4 válida y nadie más debe estar
+/// modificándola a la vez.
+pub unsafe fn mapper_for(pml4: PhysFrame) -> OffsetPageTable<'static> {
+    let offset = crate::HHDM_OFFSET.lock().expect("HHDM no inicializado");
+    OffsetPageTable::new(table_at(pml4), VirtAddr::new(offset))
+}
+
+/// Tabla de páginas en el frame `frame`, accedida por el HHDM
+///
+/// # Safety
+/// `frame` tiene que contener una tabla de páginas.
+pub unsafe fn table_at(frame: PhysFrame) -> &'static mut PageTable {
+    &mut *(crate::phys_to_virt(frame.start_address().as_u64()) as *mut PageTable)
+}
+
+fn is_active(pml4: PhysFrame) -> bool {
+    Cr3::read().0 == pml4
+}
+
+fn flush(pml4: PhysFrame, flush: MapperFlush<Size4KiB>) {
+    if is_active(pml4) {
+        flush.flush();
+    } else {
+        flush.ignore();
+    }
+}
+
+fn page_at(virt_start: VirtAddr, index: u64) -> Page {
+    Page::containing_address(virt_start + index * 4096)
+}
+
+/// Dirección física de `addr` y flags de su página, si está mapeada
+pub fn translate_in(pml4: PhysFrame, addr: VirtAddr) -> Option<(PhysAddr, PageTableFlags)> {
+    let mapper = unsafe { mapper_for(pml4) };
+    match mapper.translate(addr) {
+        TranslateResult::Mapped { frame, offset, flags } => Some((frame.start_address() + offset, flags)),
+        _ => None,
+    }
+}
+
+pub fn map_range_in(
+    pml4: PhysFrame,
+    virt_start: VirtAddr,
+    page_count: u64,
+    flags: PageTableFlags,
+) -> Result<(), &'static str> {
+    let mut mapper = unsafe { mapper_for(pml4) };
+    let mut allocator = FRAME_ALLOCATOR.lock();
+
+    for i in 0..page_count {
+        let page = page_at(virt_start, i);
+        let frame = allocator.allocate_frame().ok_or("No hay frames disponibles")?;
+
+        // No dejar restos de otros usos del frame
+        unsafe {
+            core::ptr::write_bytes(crate::phys_to_virt(frame.start_address().as_u64()) as *mut u8, 0, 4096);
+            match mapper.map_to(page, frame, flags, &mut *allocator) {
+                Ok(result) => flush(pml4, result),
+                Err(_) => {
+                    allocator.deallocate_frame(frame);
+                    return Err("Error al mapear");
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Desmapea y libera los frames. Las páginas que no estaban mapeadas se
+/// ignoran, y las tablas intermedias que quedan vacías se liberan también.
+///
+/// Se recorren solo las tablas que existen, así que el coste depende de lo
+/// mapeado y no del tamaño del rango.
+pub fn unmap_range_in(pml4: PhysFrame, virt_start: VirtAddr, page_count: u64) -> Result<(), &'static str> {
+    let start = virt_start.align_down(4096u64).as_u64();
+    let end = page_count.checked_mul(4096)
+        .and_then(|len| start.checked_add(len))
+        .filter(|&end| end <= LOWER_HALF_END)
+        .ok_or("Rango fuera de la mitad baja")?;
+    if start == end {
+        return Ok(());
+    }
+
+    let mut allocator = FRAME_ALLOCATOR.lock();
+    let range = Walk { start, end, active: is_active(pml4) };
+    unsafe { range.unmap(table_at(pml4), 4, 0, &mut allocator) }
+}
+
+/// Primera página mapeada de `[start, end)` en la mitad baja, mirando solo
+/// las tablas que existen
+pub fn first_mapped_in(pml4: PhysFrame, start: VirtAddr, end: VirtAddr) -> Option<VirtAddr> {
+    let start = start.align_down(4096u64).as_u64();
+    let end = end.as_u64().min(LOWER_HALF_END);
+    if start >= end {
+        return None;
+    }
+    let range = Walk { start, end, active: false };
+    unsafe { range.first_mapped(table_at(pml4), 4, 0) }.map(VirtAddr::new)
+}
+
+// Final de la mitad baja (la primera entrada del kernel en la PML4)
+const LOWER_HALF_END: u64 = (KERNEL_HALF as u64) << 39;
+
+/// Rango `[start, end)` de la mitad baja (alineado a página) que se recorre
+/// bajando solo por las entradas presentes
+struct Walk {
+    start: u64,
+    end: u64,
+    // La PML4 está en CR3: hay que invalidar el TLB
+    active: bool,
+}
+
+impl Walk {
+    /// Entradas de una tabla de nivel `level` que empieza en `base` que
+    /// tocan el rango, con la primera dirección que cubre cada una
+    fn entries(&self, level: u8, base: u64) -> impl Iterator<Item = (usize, u64)> {
+        let span = 1u64 << (12 + 9 * (level as u64 - 1));
+        let first = self.start.saturating_sub(base) / span;
+        let last = (self.end - base).div_ceil(span).min(512);
+        (first..last).map(move |i| (i as usize, base + i * span))
+    }
+
+    /// # Safety
+    /// `table` es de nivel `level` en la mitad baja y ninguna de las tablas
+    /// que cuelgan de ella la usa otra PML4.
+    unsafe fn unmap(
+        &self,
+        table: &mut PageTable,
+        level: u8,
+        base: u64,
+        allocator: &mut BitmapFrameAllocator,
+    ) -> Result<(), &'static str> {
+        for (index, addr) in self.entries(level, base) {
+            let entry = &mut table[index];
+            let flags = entry.flags();
+            if !flags.contains(PageTableFlags::PRESENT) {
+                continue;
+            }
+            let frame = PhysFrame::containing_address(entry.addr());
+            if level == 1 {
+                entry.set_unused();
+                allocator.release(frame);
+                self.flush(addr);
+                continue;
+            }
+            // Nunca creamos páginas grandes en la mitad baja
+            if flags.contains(PageTableFlags::HUGE_PAGE) {
+                return Err("Error al desmapear");
+            }
+
+            let child = table_at(frame);
+            self.unmap(child, level - 1, addr, allocator)?;
+            if is_empty(child) {
+                entry.set_unused();
+                allocator.deallocate_frame(frame);
+                // Las cachés de estructuras de paginación también guardan tablas
+                self.flush(addr);
+            }
+        }
+        Ok(())
+    }
+
+    /// # Safety
+    /// `table` es de nivel `level` en la mitad baja.
+    unsafe fn first_mapped(&self, table: &PageTable, level: u8, base: u64) -> Option<u64> {
+        for (index, addr) in self.entries(level, base) {
+            let entry = &table[index];
+            let flags = entry.flags();
+            if !flags.contains(PageTableFlags::PRESENT) {
+                continue;
+            }
+            if level == 1 || flags.contains(PageTableFlags::HUGE_PAGE) {
+                return Some(addr.max(self.start));
+            }
+            let child = table_at(PhysFrame::containing_address(entry.addr()));
+            if let Some(found) = self.first_mapped(child, level - 1, addr) {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    fn flush(&self, addr: u64) {
+        if self.active {
+            tlb::flush(VirtAddr::new(addr));
+        }
+    }
+}
+
+/// Cambia los flags de páginas que ya están mapeadas
+pub fn protect_range_in(
+    pml4: PhysFrame,
+    virt_start: VirtAddr,
+    page_count: u64,
+    flags: PageTableFlags,
+) -> Result<(), &'static str> {
+    let mut mapper = unsafe { mapper_for(pml4) };
+
+    for i in 0..page_count {
+        let result = unsafe { mapper.update_flags(page_at(virt_start, i), flags) }
+            .map_err(|_| "Página no mapeada")?;
+        flush(pml4, result);
+    }
+    Ok(())
+}
+
+/// Pone `frame` en lugar del frame que hay mapeado en `page` y devuelve el
+/// anterior (que sigue siendo de quien llama)
+pub fn replace_frame_in(
+    pml4: PhysFrame,
+    page: VirtAddr,
+    frame: PhysFrame,
+    flags: PageTableFlags,
+) -> Result<PhysFrame, &'static str> {
+    let mut mapper = unsafe { mapper_for(pml4) };
+    let page = Page::containing_address(page);
+    let (old, result) = mapper.unmap(page).map_err(|_| "Página no mapeada")?;
+    flush(pml4, result);
+
+    // Las tablas intermedias ya existen: no se va a reservar ningún frame
+    let mut allocator = FRAME_ALLOCATOR.lock();
+    let result = unsafe { mapper.map_to(page, frame, flags, &mut *allocator) }
+        .map_err(|_| "Error al mapear")?;
+    flush(pml4, result);
+    Ok(old)
+}
+
+fn is_empty(table: &PageTable) -> bool {
+    table.iter().all(|entry| entry.is_unused())
+}
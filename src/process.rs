@@ -0,0 +1,388 @@
+// src/process.rs
+//! Tabla de procesos
+//!
+//! Un proceso es una tarea del planificador con espacio de direcciones,
+//! padre y código de salida. Su pid es el id de esa tarea. Al terminar se
+//! libera todo menos la entrada de la tabla, que queda como zombi hasta que
+//! el padre recoge el código con `waitpid`.
+//!
+//! Los programas de usuario llegan aquí por `fork`, `execve` y `wait4`.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::elf::{ElfLoader, LoadedProgram};
+use crate::file::{File, FdTable};
+use crate::memory::AddressSpace;
+use crate::sync::IrqMutex;
+use crate::syscall::{Errno, SyscallContext};
+use crate::task::{self, TaskId};
+
+pub type Pid = TaskId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Running,
+    /// Terminado, esperando a que el padre recoja su código de salida
+    Zombie,
+}
+
+/// Cómo terminó un proceso
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Salió por su cuenta (`exit`) con este código
+    Code(i32),
+    /// Lo terminó el kernel con esta señal
+    Signal(u8),
+}
+
+impl ExitStatus {
+    /// `wstatus` de `wait4`: el código en el segundo byte (WIFEXITED) o la
+    /// señal en el primero (WIFSIGNALED)
+    pub fn wait_status(self) -> i32 {
+        match self {
+            ExitStatus::Code(code) => (code & 0xff) << 8,
+            ExitStatus::Signal(signal) => signal as i32,
+        }
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExitStatus::Code(code) => write!(f, "código {}", code),
+            ExitStatus::Signal(signal) => write!(f, "señal {}", signal),
+        }
+    }
+}
+
+/// Último paso de un proceso, que no vuelve. Se da desde un marco de pila
+/// que ya no tiene nada que liberar (ver `task::spawn`).
+enum Tail {
+    /// Empezar el programa que cargó `execve` (o `spawn_elf`)
+    Exec(LoadedProgram),
+    /// Volver a ring 3 con estos registros (el hijo de `fork`)
+    Return(SyscallContext),
+    Exit(i32),
+}
+
+pub struct Process {
+    pub pid: Pid,
+    pub parent: Option<Pid>,
+    pub name: String,
+    pub state: ProcessState,
+    pub exit_status: Option<ExitStatus>,
+    /// Escribir en la consola cada syscall (ver `syscall::trace`)
+    pub trace: bool,
+    address_space: Option<AddressSpace>,
+    files: FdTable,
+    tail: Option<Tail>,
+}
+
+impl Process {
+    fn new(pid: Pid, parent: Option<Pid>, name: &str) -> Self {
+        Process {
+            pid,
+            parent,
+            name: String::from(name),
+            state: ProcessState::Running,
+            exit_status: None,
+            trace: false,
+            address_space: None,
+            files: FdTable::new(),
+            tail: None,
+        }
+    }
+}
+
+// Orden de locks: PROCESSES antes que el del planificador, nunca al revés
+static PROCESSES: IrqMutex<BTreeMap<Pid, Process>> = IrqMutex::new(BTreeMap::new());
+
+/// Registra la tarea de arranque como el proceso del kernel
+pub fn init() {
+    let pid = task::current_id();
+    PROCESSES.lock().insert(pid, Process::new(pid, None, "kernel"));
+}
+
+pub fn current_pid() -> Pid {
+    task::current_id()
+}
+
+/// Crea un proceso hijo del actual que ejecuta `entry` en su propia tarea y
+/// sigue con lo que esta devuelva (si no sale antes por su cuenta).
+fn spawn(name: &str, entry: impl FnOnce() -> Tail + Send + 'static) -> Pid {
+    let parent = current_pid();
+    // Con la tabla cogida (y las interrupciones desactivadas) la tarea no
+    // puede empezar antes de que su entrada exista
+    let mut processes = PROCESSES.lock();
+    let trace = processes.get(&parent).is_some_and(|process| process.trace);
+    let pid = task::spawn(move || set_tail(entry()), finish);
+    let mut process = Process::new(pid, Some(parent), name);
+    process.trace = trace;
+    processes.insert(pid, process);
+    pid
+}
+
+/// Carga el ELF en un proceso nuevo con los argumentos `argv` (el primero,
+/// por convención, el nombre del programa) y sin entorno
+pub fn spawn_elf(name: &str, file: &'static [u8], argv: Vec<String>) -> Pid {
+    spawn(name, move || match ElfLoader::load(file, &argv, &[]) {
+        Ok(program) => Tail::Exec(program),
+        Err(e) => {
+            crate::println!("❌ Error al ejecutar programa: {}", e);
+            Tail::Exit(1)
+        }
+    })
+}
+
+/// Deja `tail` como lo siguiente que hará el proceso actual
+fn set_tail(tail: Tail) {
+    let pid = current_pid();
+    if let Some(process) = PROCESSES.lock().get_mut(&pid) {
+        process.tail = Some(tail);
+    }
+}
+
+fn take_tail() -> Option<Tail> {
+    let pid = current_pid();
+    PROCESSES.lock().get_mut(&pid).and_then(|process| process.tail.take())
+}
+
+/// Final de la tarea de un proceso (ver `task::spawn`)
+fn finish() -> ! {
+    match take_tail() {
+        Some(Tail::Exec(program)) => ElfLoader::execute(program),
+        Some(Tail::Return(ctx)) => unsafe { crate::usermode::return_to_user(&ctx) },
+        Some(Tail::Exit(code)) => exit(code),
+        None => exit(0),
+    }
+}
+
+/// Si el proceso actual acaba de hacer un `execve` con éxito, pasa a
+/// ejecutar el programa nuevo (y no vuelve). Se llama al salir de la
+/// syscall, cuando en la pila ya no queda nada suyo que liberar.
+pub fn finish_exec() {
+    let pid = current_pid();
+    let program = {
+        let mut processes = PROCESSES.lock();
+        let Some(process) = processes.get_mut(&pid) else { return };
+        match process.tail.take() {
+            Some(Tail::Exec(program)) => program,
+            tail => {
+                process.tail = tail;
+                return;
+            }
+        }
+    };
+    ElfLoader::execute(program)
+}
+
+/// Duplica el proceso actual (que está en la syscall `ctx`). El hijo comparte
+/// la memoria en copy-on-write y vuelve a ring 3 con los mismos registros,
+/// salvo RAX = 0.
+pub fn fork(ctx: &SyscallContext) -> Result<Pid, Errno> {
+    let parent = current_pid();
+    let mut processes = PROCESSES.lock();
+    let process = processes.get_mut(&parent).ok_or(Errno::ESRCH)?;
+    let space = process.address_space.as_mut()
+        .ok_or(Errno::EINVAL)?
+        .fork()
+        .map_err(|_| Errno::ENOMEM)?;
+    let name = process.name.clone();
+    let files = process.files.clone();
+    let trace = process.trace;
+
+    let mut child_ctx = *ctx;
+    child_ctx.rax = 0;
+    let fs_base = task::fs_base();
+    let pid = task::spawn(move || {
+        set_address_space(space);
+        task::set_fs_base(fs_base);
+        set_tail(Tail::Return(child_ctx));
+    }, finish);
+    task::inherit_fx_state(pid);
+    let mut child = Process::new(pid, Some(parent), &name);
+    child.files = files;
+    child.trace = trace;
+    processes.insert(pid, child);
+    Ok(pid)
+}
+
+/// Carga el programa `path` para sustituir la imagen del proceso actual. El
+/// cambio se hace al salir de la syscall (ver `finish_exec`); si no se pudo
+/// cargar, el proceso sigue como estaba.
+pub fn exec(path: &str, argv: &[String], envp: &[String]) -> Result<(), Errno> {
+    let (name, file) = crate::programs::find(path).ok_or(Errno::ENOENT)?;
+    let program = ElfLoader::load(file, argv, envp).map_err(|e| {
+        crate::println!("execve {}: {}", path, e);
+        e.errno()
+    })?;
+
+    let pid = current_pid();
+    if let Some(process) = PROCESSES.lock().get_mut(&pid) {
+        process.name = String::from(name);
+        process.tail = Some(Tail::Exec(program));
+    }
+    Ok(())
+}
+
+/// `true` si hay que trazar las syscalls del proceso actual
+pub fn is_traced() -> bool {
+    let pid = current_pid();
+    PROCESSES.lock().get(&pid).is_some_and(|process| process.trace)
+}
+
+/// Activa o desactiva la traza de syscalls de `pid` (los hijos que cree
+/// después la heredan)
+pub fn set_trace(pid: Pid, enabled: bool) -> Result<(), Errno> {
+    let mut processes = PROCESSES.lock();
+    processes.get_mut(&pid).ok_or(Errno::ESRCH)?.trace = enabled;
+    Ok(())
+}
+
+/// Padre del proceso actual (0 si no tiene, como en Linux)
+pub fn parent_pid() -> Pid {
+    let pid = current_pid();
+    PROCESSES.lock().get(&pid).and_then(|process| process.parent).unwrap_or(0)
+}
+
+/// Asocia `space` al proceso actual y lo activa. El anterior, si lo había,
+/// se libera.
+pub fn set_address_space(space: AddressSpace) {
+    let pid = current_pid();
+    let old = {
+        let mut processes = PROCESSES.lock();
+        let process = processes.get_mut(&pid).expect("La tarea actual no es un proceso");
+        task::set_page_table(Some(space.pml4()));
+        process.address_space.replace(space)
+    };
+    drop(old);
+}
+
+/// Archivo abierto en el descriptor `fd` del proceso actual
+pub fn file(fd: u32) -> Option<Arc<File>> {
+    let pid = current_pid();
+    PROCESSES.lock().get(&pid)?.files.get(fd)
+}
+
+/// Abre `file` en el descriptor libre más bajo del proceso actual
+pub fn open_file(file: File) -> Result<u32, Errno> {
+    let pid = current_pid();
+    PROCESSES.lock().get_mut(&pid).ok_or(Errno::ESRCH)?.files.insert(file)
+}
+
+pub fn close_file(fd: u32) -> Result<(), Errno> {
+    let pid = current_pid();
+    PROCESSES.lock().get_mut(&pid).ok_or(Errno::ESRCH)?.files.close(fd)
+}
+
+/// Ejecuta `f` con el espacio de direcciones del proceso actual
+pub fn with_current_space<R>(f: impl FnOnce(&mut AddressSpace) -> R) -> Result<R, &'static str> {
+    let pid = current_pid();
+    let mut processes = PROCESSES.lock();
+    let space = processes.get_mut(&pid)
+        .and_then(|process| process.address_space.as_mut())
+        .ok_or("El proceso no tiene espacio de direcciones")?;
+    Ok(f(space))
+}
+
+/// Ejecuta `f` con el espacio de direcciones del proceso actual. Devuelve
+/// `None` si no tiene uno o si la tabla está bloqueada (el manejador de page
+/// faults no puede esperar a que se libere).
+pub fn try_with_current_space<R>(f: impl FnOnce(&mut AddressSpace) -> R) -> Option<R> {
+    let pid = current_pid();
+    let mut processes = PROCESSES.try_lock()?;
+    let space = processes.get_mut(&pid)?.address_space.as_mut()?;
+    Some(f(space))
+}
+
+/// Termina el proceso actual con `exit(code)`
+pub fn exit(code: i32) -> ! {
+    terminate(ExitStatus::Code(code))
+}
+
+/// Termina el proceso actual como si hubiera recibido `signal`
+pub fn kill(signal: u8) -> ! {
+    terminate(ExitStatus::Signal(signal))
+}
+
+/// Libera la memoria del proceso actual, avisa al padre y cede el CPU
+fn terminate(status: ExitStatus) -> ! {
+    let pid = current_pid();
+    let space = {
+        let mut processes = PROCESSES.lock();
+
+        // Los hijos se quedan sin padre; los zombis ya no los va a recoger nadie
+        processes.retain(|_, child| child.parent != Some(pid) || child.state != ProcessState::Zombie);
+        for child in processes.values_mut().filter(|child| child.parent == Some(pid)) {
+            child.parent = None;
+        }
+
+        let process = processes.get_mut(&pid).expect("La tarea actual no es un proceso");
+        process.state = ProcessState::Zombie;
+        process.exit_status = Some(status);
+        let space = process.address_space.take();
+        process.files.close_all();
+        match process.parent {
+            Some(parent) => task::wake(parent),
+            None => { processes.remove(&pid); }
+        }
+        space
+    };
+
+    // Primero se deja de usar la PML4 y después se libera
+    task::set_page_table(None);
+    drop(space);
+    task::exit()
+}
+
+/// Espera a que termine un hijo del proceso actual (`pid`, o cualquiera si es
+/// `None`) y devuelve su pid y cómo terminó.
+pub fn waitpid(pid: Option<Pid>) -> Result<(Pid, ExitStatus), Errno> {
+    loop {
+        if let Some(child) = reap(pid, true)? {
+            return Ok(child);
+        }
+        task::yield_now();
+    }
+}
+
+/// Como `waitpid`, pero sin esperar (WNOHANG): `None` si ningún hijo ha
+/// terminado todavía
+pub fn try_waitpid(pid: Option<Pid>) -> Result<Option<(Pid, ExitStatus)>, Errno> {
+    reap(pid, false)
+}
+
+/// Recoge un hijo zombi. Si no hay ninguno y `block`, deja la tarea marcada
+/// como bloqueada hasta que termine uno.
+fn reap(pid: Option<Pid>, block: bool) -> Result<Option<(Pid, ExitStatus)>, Errno> {
+    let parent = current_pid();
+    let mut processes = PROCESSES.lock();
+    let mut children = processes.values()
+        .filter(|child| child.parent == Some(parent) && pid.is_none_or(|pid| child.pid == pid))
+        .peekable();
+    if children.peek().is_none() {
+        return Err(Errno::ECHILD);
+    }
+    if let Some(zombie) = children.find(|child| child.state == ProcessState::Zombie) {
+        let zombie = zombie.pid;
+        let process = processes.remove(&zombie).unwrap();
+        return Ok(Some((zombie, process.exit_status.unwrap_or(ExitStatus::Code(0)))));
+    }
+    // Marcarse bloqueado con la tabla cogida: si el hijo termina antes de
+    // que cedamos el CPU, su `wake` nos vuelve a poner en la cola
+    if block {
+        task::mark_blocked();
+    }
+    Ok(None)
+}
+
+/// Pid, padre, estado y nombre de cada proceso, para depuración
+pub fn list(mut f: impl FnMut(Pid, Option<Pid>, ProcessState, &str)) {
+    let processes = PROCESSES.lock();
+    for process in processes.values() {
+        f(process.pid, process.parent, process.state, &process.name);
+    }
+}
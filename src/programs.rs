@@ -0,0 +1,76 @@
+// src/programs.rs
+//! Programas de usuario cargados por Limine como módulos (no hay sistema de
+//! archivos: `execve` los busca aquí por nombre, el cargador de ELF los
+//! intérpretes de PT_INTERP, como `/lib/ld-musl-x86_64.so.1`, y el intérprete
+//! las bibliotecas, con `open` y `mmap`)
+//!
+//! Cada módulo se declara en `limine.conf`; su cmdline, si la tiene, es el
+//! nombre con el que se lanza:
+//!
+//! ```text
+//! module_path: boot():/boot/hello.elf
+//! module_cmdline: hello
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use limine::response::ModuleResponse;
+use spin::Once;
+
+/// Módulo de arranque, con la ruta y la cmdline copiadas: las estructuras de
+/// Limine están en memoria recuperable. Los datos se quedan donde los dejó
+/// (memoria del kernel y los módulos, que no se libera nunca).
+pub struct Module {
+    pub path: String,
+    pub cmdline: String,
+    pub data: &'static [u8],
+}
+
+impl Module {
+    /// Nombre con el que se busca: la cmdline o, sin ella, el último
+    /// componente de la ruta sin `.elf`
+    pub fn name(&self) -> &str {
+        if !self.cmdline.is_empty() {
+            return &self.cmdline;
+        }
+        let file = self.path.rsplit('/').next().unwrap_or(&self.path);
+        file.strip_suffix(".elf").unwrap_or(file)
+    }
+}
+
+static MODULES: Once<Vec<Module>> = Once::new();
+
+/// Registra los módulos que cargó Limine. Requiere el heap.
+pub fn init(response: Option<&ModuleResponse>) {
+    MODULES.call_once(|| {
+        let Some(response) = response else {
+            crate::println!("⚠️  Limine no cargó ningún módulo");
+            return Vec::new();
+        };
+        response.modules().iter().map(|file| {
+            // SAFETY: Limine garantiza `size` bytes legibles en `addr` (por el HHDM)
+            let data = unsafe { core::slice::from_raw_parts(file.addr(), file.size() as usize) };
+            Module {
+                path: String::from_utf8_lossy(file.path().to_bytes()).into_owned(),
+                cmdline: String::from_utf8_lossy(file.string().to_bytes()).into_owned(),
+                data,
+            }
+        }).collect()
+    });
+}
+
+/// Todos los módulos, en el orden de `limine.conf`
+pub fn modules() -> &'static [Module] {
+    MODULES.get().map_or(&[], Vec::as_slice)
+}
+
+/// Busca un programa por ruta: la ruta completa del módulo o, si no, su
+/// nombre comparado con el último componente, así que `hello` y
+/// `/bin/hello` son el mismo.
+pub fn find(path: &str) -> Option<(&'static str, &'static [u8])> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    modules().iter()
+        .find(|module| module.path == path)
+        .or_else(|| modules().iter().find(|module| module.name() == name))
+        .map(|module| (module.name(), module.data))
+}
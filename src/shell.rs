@@ -0,0 +1,173 @@
+// src/shell.rs
+//! Órdenes de la shell del kernel (la línea la recoge `keyboard`)
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::elf::ElfFile;
+use crate::process::{self, ProcessState};
+
+pub fn execute(line: &str) {
+    reap_background();
+    let mut words = line.split_whitespace();
+    let Some(command) = words.next() else { return };
+
+    match command {
+        "help" => {
+            crate::println!("Órdenes: help, ps, modules, hello, run <programa> [args...] [&], sleep <ms>, trace <pid> on|off, readelf <programa> [dirección]");
+        }
+        "ps" => {
+            crate::println!("  PID  PPID  ESTADO      NOMBRE");
+            process::list(|pid, parent, state, name| {
+                let state = match state {
+                    ProcessState::Running => "activo",
+                    ProcessState::Zombie => "zombi",
+                };
+                match parent {
+                    Some(parent) => crate::println!("  {:>3}  {:>4}  {:<10}  {}", pid, parent, state, name),
+                    None => crate::println!("  {:>3}     -  {:<10}  {}", pid, state, name),
+                }
+            });
+        }
+        "modules" => {
+            crate::println!("  TAMAÑO  NOMBRE            RUTA");
+            for module in crate::programs::modules() {
+                crate::println!("  {:>6}  {:<16}  {}", module.data.len(), module.name(), module.path);
+            }
+        }
+        "hello" => run(&["hello"], false),
+        // Con `&` al final no se espera: así se puede trazar mientras corre
+        "run" => {
+            let mut args: Vec<&str> = words.collect();
+            let background = args.last() == Some(&"&");
+            if background {
+                args.pop();
+            }
+            if args.is_empty() {
+                crate::println!("Uso: run <programa> [argumentos...] [&]");
+            } else {
+                run(&args, background);
+            }
+        }
+        "sleep" => match words.next().and_then(|ms| ms.parse().ok()) {
+            Some(ms) => crate::task::sleep_ms(ms),
+            None => crate::println!("Uso: sleep <ms>"),
+        },
+        // Con el pid de la shell (0) se trazan los programas que lance después
+        "trace" => {
+            let pid = words.next().and_then(|pid| pid.parse().ok());
+            let enabled = match words.next() {
+                Some("on") => Some(true),
+                Some("off") => Some(false),
+                _ => None,
+            };
+            match (pid, enabled) {
+                (Some(pid), Some(enabled)) => match process::set_trace(pid, enabled) {
+                    Ok(()) => crate::println!("Traza de {} {}", pid, if enabled { "activada" } else { "desactivada" }),
+                    Err(e) => crate::println!("❌ {}", e),
+                },
+                _ => crate::println!("Uso: trace <pid> on|off"),
+            }
+        }
+        "readelf" => {
+            let name = words.next();
+            let addr = words.next().map(|addr| u64::from_str_radix(addr.trim_start_matches("0x"), 16));
+            match (name, addr) {
+                (Some(name), None) => readelf(name, None),
+                (Some(name), Some(Ok(addr))) => readelf(name, Some(addr)),
+                _ => crate::println!("Uso: readelf <programa> [dirección en hex]"),
+            }
+        }
+        _ => crate::println!("Orden desconocida: {}", command),
+    }
+}
+
+/// Lanza el programa `args[0]` con `args` como argv y, si no va en segundo
+/// plano, espera a que termine
+fn run(args: &[&str], background: bool) {
+    let Some((name, file)) = crate::programs::find(args[0]) else {
+        crate::println!("❌ {}: {}", args[0], crate::syscall::Errno::ENOENT);
+        return;
+    };
+    let argv = args.iter().map(|&arg| String::from(arg)).collect();
+    let pid = process::spawn_elf(name, file, argv);
+    if background {
+        crate::println!("[{}] en segundo plano", pid);
+        return;
+    }
+    match process::waitpid(Some(pid)) {
+        Ok((pid, status)) => crate::println!("Proceso {} terminado con {}", pid, status),
+        Err(e) => crate::println!("❌ {}", e),
+    }
+}
+
+/// Recoge los programas en segundo plano que hayan terminado desde la
+/// última orden
+fn reap_background() {
+    while let Ok(Some((pid, status))) = process::try_waitpid(None) {
+        crate::println!("[{}] terminado con {}", pid, status);
+    }
+}
+
+/// Muestra las cabeceras, secciones, símbolos y notas de un programa o, con
+/// `addr`, el símbolo que la contiene
+fn readelf(name: &str, addr: Option<u64>) {
+    let Some((_, file)) = crate::programs::find(name) else {
+        crate::println!("❌ {}: {}", name, crate::syscall::Errno::ENOENT);
+        return;
+    };
+    let elf = match ElfFile::parse(file) {
+        Ok(elf) => elf,
+        Err(e) => {
+            crate::println!("❌ {}: {}", name, e);
+            return;
+        }
+    };
+
+    if let Some(addr) = addr {
+        match elf.symbolize(addr) {
+            Some((symbol, offset)) => crate::println!("0x{:x} = {}+0x{:x}", addr, symbol.name, offset),
+            None => crate::println!("0x{:x}: sin símbolo", addr),
+        }
+        return;
+    }
+
+    let header = elf.header();
+    crate::println!(
+        "{:?} {} {}, entrada 0x{:x}, flags 0x{:x}",
+        elf.class(), header.type_name(), header.machine_name(), header.e_entry, header.e_flags,
+    );
+
+    crate::println!("Segmentos:");
+    crate::println!("  TIPO      DESPL.    VIRT.             ARCHIVO   MEMORIA   PERM  ALIN.");
+    for phdr in elf.program_headers() {
+        let perms: String = phdr.permissions().iter().collect();
+        crate::println!(
+            "  {:<8}  {:>8x}  {:>16x}  {:>8x}  {:>8x}  {}   {:x}",
+            phdr.type_name(), phdr.p_offset, phdr.p_vaddr, phdr.p_filesz, phdr.p_memsz, perms, phdr.p_align,
+        );
+    }
+
+    crate::println!("Secciones:");
+    crate::println!("  NOMBRE              TIPO      DIRECCIÓN         DESPL.    TAMAÑO    ENT  FLAGS  LINK  INFO  ALIN.");
+    for section in elf.section_headers() {
+        crate::println!(
+            "  {:<18}  {:<8}  {:>16x}  {:>8x}  {:>8x}  {:>3x}  {:>5x}  {:>4}  {:>4}  {:x}",
+            elf.section_name(&section).unwrap_or("?"), section.type_name(), section.sh_addr,
+            section.sh_offset, section.sh_size, section.sh_entsize, section.sh_flags,
+            section.sh_link, section.sh_info, section.sh_addralign,
+        );
+    }
+
+    crate::println!("Símbolos:");
+    for symbol in elf.symbols().filter(|symbol| symbol.is_addressable()) {
+        crate::println!("  {:>16x}  {:>6}  {:<6}  {}", symbol.value, symbol.size, symbol.kind_name(), symbol.name);
+    }
+
+    crate::println!("Notas:");
+    for note in elf.notes() {
+        crate::println!(
+            "  {:<8}  tipo {}  {} bytes",
+            String::from_utf8_lossy(note.name), note.n_type, note.desc.len(),
+        );
+    }
+}
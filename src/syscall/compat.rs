@@ -0,0 +1,126 @@
+// src/syscall/compat.rs
+//! Syscalls de los programas i386 (modo compatibilidad) por `int 0x80`
+//!
+//! El número llega en EAX y los argumentos en EBX, ECX, EDX, ESI, EDI y EBP.
+//! Se traducen a la llamada equivalente de x86_64 y se atienden con el mismo
+//! `SyscallHandler`; el resultado vuelve en EAX.
+
+use crate::interrupts::InterruptFrame;
+use super::{Abi, Errno, KernelSyscalls, SyscallContext, SyscallHandler};
+use super::{SYS_READ, SYS_WRITE, SYS_OPEN, SYS_CLOSE, SYS_EXIT, SYS_EXECVE, SYS_WAIT4};
+use super::{SYS_MMAP, SYS_MUNMAP, SYS_BRK, SYS_RT_SIGACTION, SYS_RT_SIGPROCMASK, SYS_IOCTL, SYS_WRITEV};
+use super::{SYS_GETPID, SYS_GETPPID, SYS_GETUID, SYS_GETGID, SYS_GETEUID, SYS_GETEGID};
+use super::{SYS_SET_TID_ADDRESS, SYS_EXIT_GROUP};
+
+// Números de Linux para i386 (`unistd_32.h`)
+const I386_EXIT: u32 = 1;
+const I386_READ: u32 = 3;
+const I386_WRITE: u32 = 4;
+const I386_OPEN: u32 = 5;
+const I386_CLOSE: u32 = 6;
+const I386_WAITPID: u32 = 7;
+const I386_EXECVE: u32 = 11;
+const I386_GETPID: u32 = 20;
+const I386_GETUID: u32 = 24;
+const I386_BRK: u32 = 45;
+const I386_GETGID: u32 = 47;
+const I386_GETEUID: u32 = 49;
+const I386_GETEGID: u32 = 50;
+const I386_IOCTL: u32 = 54;
+const I386_GETPPID: u32 = 64;
+const I386_MUNMAP: u32 = 91;
+const I386_WAIT4: u32 = 114;
+const I386_WRITEV: u32 = 146;
+const I386_RT_SIGACTION: u32 = 174;
+const I386_RT_SIGPROCMASK: u32 = 175;
+const I386_MMAP2: u32 = 192;
+const I386_GETUID32: u32 = 199;
+const I386_GETGID32: u32 = 200;
+const I386_GETEUID32: u32 = 201;
+const I386_GETEGID32: u32 = 202;
+const I386_EXIT_GROUP: u32 = 252;
+const I386_SET_TID_ADDRESS: u32 = 258;
+
+/// Syscall de x86_64 equivalente. `fork` no está: el hijo volvería a ring 3
+/// con el selector de 64 bits (ver `usermode::return_to_user`).
+fn translate(num: u32) -> Option<usize> {
+    let num = match num {
+        I386_EXIT => SYS_EXIT,
+        I386_READ => SYS_READ,
+        I386_WRITE => SYS_WRITE,
+        I386_OPEN => SYS_OPEN,
+        I386_CLOSE => SYS_CLOSE,
+        // waitpid(pid, status, options) es wait4 sin rusage
+        I386_WAITPID | I386_WAIT4 => SYS_WAIT4,
+        I386_EXECVE => SYS_EXECVE,
+        I386_GETPID => SYS_GETPID,
+        I386_GETUID | I386_GETUID32 => SYS_GETUID,
+        I386_BRK => SYS_BRK,
+        I386_GETGID | I386_GETGID32 => SYS_GETGID,
+        I386_GETEUID | I386_GETEUID32 => SYS_GETEUID,
+        I386_GETEGID | I386_GETEGID32 => SYS_GETEGID,
+        I386_IOCTL => SYS_IOCTL,
+        I386_GETPPID => SYS_GETPPID,
+        I386_MUNMAP => SYS_MUNMAP,
+        I386_WRITEV => SYS_WRITEV,
+        I386_RT_SIGACTION => SYS_RT_SIGACTION,
+        I386_RT_SIGPROCMASK => SYS_RT_SIGPROCMASK,
+        I386_MMAP2 => SYS_MMAP,
+        I386_EXIT_GROUP => SYS_EXIT_GROUP,
+        I386_SET_TID_ADDRESS => SYS_SET_TID_ADDRESS,
+        _ => return None,
+    };
+    Some(num)
+}
+
+/// Argumento con signo (un pid o un fd) extendido a 64 bits
+fn sign_extend(value: u64) -> u64 {
+    value as u32 as i32 as i64 as u64
+}
+
+/// Atiende un `int 0x80` desde modo compatibilidad
+pub fn handle_int80(frame: &mut InterruptFrame) {
+    // La puerta las desactiva, pero el marco ya está en la pila de la tarea
+    x86_64::instructions::interrupts::enable();
+    let num = frame.rax as u32;
+    let Some(syscall_num) = translate(num) else {
+        crate::println!("Syscall i386 {} no implementada", num);
+        frame.rax = Errno::ENOSYS.as_return() as u64;
+        return;
+    };
+
+    // Los registros de 32 bits se extienden con ceros: así se quedan los
+    // punteros, que es lo que llevan casi todos los argumentos
+    let reg = |value: u64| value as u32 as u64;
+    let mut ctx = SyscallContext {
+        rax: syscall_num as u64,
+        rdi: reg(frame.rbx),
+        rsi: reg(frame.rcx),
+        rdx: reg(frame.rdx),
+        r10: reg(frame.rsi),
+        r8: reg(frame.rdi),
+        r9: reg(frame.rbp),
+        rbx: frame.rbx,
+        rbp: frame.rbp,
+        r12: frame.r12,
+        r13: frame.r13,
+        r14: frame.r14,
+        r15: frame.r15,
+        rip: frame.rip,
+        rflags: frame.rflags,
+        rsp: frame.rsp,
+    };
+    match syscall_num {
+        SYS_WAIT4 => ctx.rdi = sign_extend(ctx.rdi),
+        // mmap2 recibe el desplazamiento en páginas
+        SYS_MMAP => {
+            ctx.r8 = sign_extend(ctx.r8);
+            ctx.r9 *= 4096;
+        }
+        _ => {}
+    }
+
+    let mut syscalls = KernelSyscalls::new();
+    frame.rax = SyscallHandler::handle(&ctx, &mut syscalls, Abi::I386) as u32 as u64;
+    crate::process::finish_exec();
+}
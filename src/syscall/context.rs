@@ -1,5 +1,10 @@
 //! Contexto de ejecución para syscalls (útil para guardar estado)
+//!
+//! El orden de los campos es el mismo en que `entry.rs` deja los registros
+//! en la pila del kernel: no reordenar sin tocar el stub.
 
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct SyscallContext {
     pub rax: u64,
     pub rdi: u64,
@@ -8,17 +13,13 @@ pub struct SyscallContext {
     pub r10: u64,
     pub r8: u64,
     pub r9: u64,
+    pub rbx: u64,
+    pub rbp: u64,
+    pub r12: u64,
+    pub r13: u64,
+    pub r14: u64,
+    pub r15: u64,
     pub rip: u64,
-    pub rsp: u64,
     pub rflags: u64,
-}
-
-impl SyscallContext {
-    pub fn new() -> Self {
-        Self {
-            rax: 0, rdi: 0, rsi: 0, rdx: 0,
-            r10: 0, r8: 0, r9: 0,
-            rip: 0, rsp: 0, rflags: 0,
-        }
-    }
+    pub rsp: u64,
 }
@@ -0,0 +1,161 @@
+//! Punto de entrada de la instrucción SYSCALL
+//!
+//! La CPU salta a `syscall_entry` con RCX = RIP de usuario, R11 = RFLAGS y
+//! la pila del usuario todavía en RSP. El stub cambia a la pila del kernel,
+//! guarda los registros como un `SyscallContext` y llama al dispatcher, que
+//! vuelve a activar las interrupciones: el resto de la syscall se puede
+//! interrumpir y la tarea, ceder el CPU.
+//!
+//! SYSCALL desde modo compatibilidad (solo en AMD; Intel lanza #UD) salta a
+//! `syscall_compat_entry`, que devuelve -ENOSYS: los programas de 32 bits
+//! usan `int 0x80` (ver `compat`).
+
+use core::arch::global_asm;
+use core::ptr::addr_of;
+use x86_64::registers::model_specific::{Efer, EferFlags, LStar, Msr, SFMask, Star};
+use x86_64::registers::rflags::RFlags;
+use x86_64::instructions::interrupts;
+use x86_64::VirtAddr;
+use super::{Abi, Errno, SyscallContext, SyscallHandler, KernelSyscalls};
+
+const KERNEL_STACK_SIZE: usize = 16 * 1024;
+
+/// CSTAR: destino de SYSCALL en modo compatibilidad (el crate no lo expone)
+const CSTAR: u32 = 0xC000_0083;
+
+static mut SYSCALL_STACK: [u8; KERNEL_STACK_SIZE] = [0; KERNEL_STACK_SIZE];
+
+// Usados solo desde el stub (un único CPU)
+static mut KERNEL_RSP: u64 = 0;
+static mut USER_RSP: u64 = 0;
+// Selectores de usuario para volver por IRETQ (ver el stub)
+static mut USER_CS: u64 = 0;
+static mut USER_SS: u64 = 0;
+
+extern "C" {
+    fn syscall_entry();
+    fn syscall_compat_entry();
+}
+
+global_asm!(
+    ".global syscall_entry",
+    "syscall_entry:",
+    "mov [rip + {user_rsp}], rsp",
+    "mov rsp, [rip + {kernel_rsp}]",
+    // Construir SyscallContext (del último campo al primero)
+    "push qword ptr [rip + {user_rsp}]",
+    "push r11",
+    "push rcx",
+    "push r15",
+    "push r14",
+    "push r13",
+    "push r12",
+    "push rbp",
+    "push rbx",
+    "push r9",
+    "push r8",
+    "push r10",
+    "push rdx",
+    "push rsi",
+    "push rdi",
+    "push rax",
+    "mov rdi, rsp",
+    "call {dispatch}",
+    // El resto del camino de vuelta no puede interrumpirse: acaba con la
+    // pila del usuario cargada todavía en ring 0
+    "cli",
+    "pop rax",
+    "pop rdi",
+    "pop rsi",
+    "pop rdx",
+    "pop r10",
+    "pop r8",
+    "pop r9",
+    "pop rbx",
+    "pop rbp",
+    "pop r12",
+    "pop r13",
+    "pop r14",
+    "pop r15",
+    // SYSRET con un RCX no canónico lanza #GP en ring 0, pero ya con la pila
+    // del usuario (CVE-2012-0217). Si el RIP de vuelta no está en la mitad
+    // baja, se vuelve por IRETQ.
+    "mov rcx, [rsp]",
+    "shr rcx, 47",
+    "jnz 2f",
+    "pop rcx",
+    "pop r11",
+    "pop rsp",
+    "sysretq",
+    // Quedan rip, rflags y rsp del contexto: se convierten en el marco de
+    // IRETQ (rip, cs, rflags, rsp, ss) usando los huecos ya sacados de debajo
+    "2:",
+    "sub rsp, 16",
+    "mov rcx, [rsp + 16]",
+    "mov [rsp], rcx",
+    "mov r11, [rsp + 24]",
+    "mov [rsp + 16], r11",
+    "mov r11, [rsp + 32]",
+    "mov [rsp + 24], r11",
+    "mov r11, [rip + {user_ss}]",
+    "mov [rsp + 32], r11",
+    "mov r11, [rip + {user_cs}]",
+    "mov [rsp + 8], r11",
+    "mov r11, [rsp + 16]",
+    "iretq",
+    user_rsp = sym USER_RSP,
+    user_cs = sym USER_CS,
+    user_ss = sym USER_SS,
+    kernel_rsp = sym KERNEL_RSP,
+    dispatch = sym syscall_dispatch,
+);
+
+// No toca la pila ni ningún registro salvo EAX: SYSRET de 32 bits vuelve a
+// ECX con RFLAGS = R11 y los selectores de 32 bits de STAR
+global_asm!(
+    ".global syscall_compat_entry",
+    "syscall_compat_entry:",
+    "mov eax, {enosys}",
+    "sysret",
+    enosys = const -(Errno::ENOSYS as i32),
+);
+
+extern "C" fn syscall_dispatch(ctx: &mut SyscallContext) {
+    // El contexto ya está en la pila de la tarea y `USER_RSP` ya no hace falta
+    interrupts::enable();
+    let mut syscalls = KernelSyscalls::new();
+    ctx.rax = SyscallHandler::handle(ctx, &mut syscalls, Abi::X86_64) as u64;
+    // Lo que reservó la syscall ya se ha liberado: si fue un execve, aquí
+    // se salta al programa nuevo
+    crate::process::finish_exec();
+}
+
+/// Pila del kernel que usará el stub en la próxima syscall
+pub fn set_kernel_stack(top: u64) {
+    unsafe { KERNEL_RSP = top };
+}
+
+/// Programa EFER.SCE, STAR, LSTAR, CSTAR y SFMASK. Requiere `gdt::init()` antes.
+pub fn init() {
+    let selectors = crate::gdt::selectors();
+    unsafe {
+        // La pila tiene que quedar alineada a 16 para el `call` del stub
+        KERNEL_RSP = (addr_of!(SYSCALL_STACK) as u64 + KERNEL_STACK_SIZE as u64) & !0xF;
+        USER_CS = selectors.user_code.0 as u64;
+        USER_SS = selectors.user_data.0 as u64;
+        Efer::update(|flags| flags.insert(EferFlags::SYSTEM_CALL_EXTENSIONS));
+    }
+    Star::write(
+        selectors.user_code,
+        selectors.user_data,
+        selectors.kernel_code,
+        selectors.kernel_data,
+    ).expect("Selectores de la GDT incompatibles con STAR");
+    LStar::write(VirtAddr::new(syscall_entry as usize as u64));
+    // SAFETY: CSTAR solo se usa desde ring 3 en modo compatibilidad, y el stub
+    // vuelve sin tocar nada del kernel
+    unsafe { Msr::new(CSTAR).write(syscall_compat_entry as usize as u64) };
+    // Entrar al kernel con interrupciones desactivadas (hasta guardar el
+    // contexto) y DF limpio
+    SFMask::write(RFlags::INTERRUPT_FLAG | RFlags::DIRECTION_FLAG | RFlags::TRAP_FLAG);
+}
@@ -0,0 +1,66 @@
+//! Códigos de error de Linux. Las syscalls los devuelven en RAX como valor
+//! negativo (-errno), que es lo que esperan libc como musl.
+
+use core::fmt;
+
+// Los nombres son los de Linux, en mayúsculas
+#[allow(clippy::upper_case_acronyms)]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Errno {
+    EPERM = 1,
+    ENOENT = 2,
+    ESRCH = 3,
+    E2BIG = 7,
+    ENOEXEC = 8,
+    EBADF = 9,
+    ECHILD = 10,
+    ENOMEM = 12,
+    EACCES = 13,
+    EFAULT = 14,
+    ENODEV = 19,
+    EINVAL = 22,
+    EMFILE = 24,
+    ENOTTY = 25,
+    EROFS = 30,
+    ENAMETOOLONG = 36,
+    ENOSYS = 38,
+}
+
+/// Resultado de una syscall: el valor a devolver o el error
+pub type SyscallResult = Result<usize, Errno>;
+
+impl Errno {
+    /// Valor de RAX para este error (-errno)
+    pub fn as_return(self) -> usize {
+        -(self as isize) as usize
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Errno::EPERM => "Operación no permitida",
+            Errno::ENOENT => "No existe el archivo o directorio",
+            Errno::ESRCH => "No existe el proceso",
+            Errno::E2BIG => "Lista de argumentos demasiado larga",
+            Errno::ENOEXEC => "Formato de ejecutable no válido",
+            Errno::EBADF => "Descriptor no válido",
+            Errno::ECHILD => "No hay procesos hijo",
+            Errno::ENOMEM => "Sin memoria",
+            Errno::EACCES => "Permiso denegado",
+            Errno::EFAULT => "Dirección no válida",
+            Errno::ENODEV => "No existe el dispositivo",
+            Errno::EINVAL => "Argumento no válido",
+            Errno::EMFILE => "Demasiados archivos abiertos",
+            Errno::ENOTTY => "No es una terminal",
+            Errno::EROFS => "Sistema de archivos de solo lectura",
+            Errno::ENAMETOOLONG => "Nombre demasiado largo",
+            Errno::ENOSYS => "Syscall no implementada",
+        }
+    }
+}
+
+impl fmt::Display for Errno {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({:?})", self.description(), self)
+    }
+}
@@ -1,45 +1,205 @@
-//! Manejador de syscalls (para cuando implementes interrupciones)
+//! Manejador de syscalls (llamado desde el stub de `entry.rs`)
+//!
+//! Los punteros del programa no se usan nunca directamente: los datos se
+//! copian a buffers del kernel con `copy_from_user`/`copy_to_user`.
 
-use super::{Syscalls, SYS_READ, SYS_WRITE, SYS_EXIT};
+use alloc::vec;
+use super::{Errno, Syscalls, SyscallContext, SyscallResult, copy_from_user, copy_to_user};
+use super::{read_user_str, read_user_str_array, read_user_word, trace};
+use super::{SYS_READ, SYS_WRITE, SYS_OPEN, SYS_CLOSE, SYS_EXIT, SYS_FORK, SYS_EXECVE, SYS_WAIT4};
+use super::{SYS_MMAP, SYS_MUNMAP, SYS_BRK, SYS_RT_SIGACTION, SYS_RT_SIGPROCMASK, SYS_IOCTL, SYS_WRITEV};
+use super::{SYS_GETPID, SYS_GETPPID, SYS_GETUID, SYS_GETGID, SYS_GETEUID, SYS_GETEGID};
+use super::{SYS_ARCH_PRCTL, SYS_SET_TID_ADDRESS, SYS_EXIT_GROUP};
+
+// Longitud máxima de una ruta que se lee de memoria de usuario
+const PATH_MAX: usize = 4096;
+
+// Tamaño de los trozos en que se copian los buffers de read/write
+const IO_CHUNK: usize = 4096;
+
+// Máximo de argumentos (y de variables de entorno) de `execve`
+const MAX_ARGS: usize = 256;
+
+// Máximo de entradas de un `writev` (IOV_MAX de Linux)
+const IOV_MAX: usize = 1024;
+
+/// Convención del programa que hace la llamada. Los números y registros ya
+/// vienen traducidos a los de x86_64 (ver `compat`); solo cambia el tamaño
+/// de los punteros dentro de las estructuras que se leen de su memoria.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Abi {
+    X86_64,
+    /// Programa de 32 bits en modo compatibilidad, entra por `int 0x80`
+    I386,
+}
+
+impl Abi {
+    /// Tamaño en bytes de un puntero o un `long`
+    fn word_size(self) -> usize {
+        match self {
+            Abi::X86_64 => 8,
+            Abi::I386 => 4,
+        }
+    }
+}
 
 pub struct SyscallHandler;
 
 impl SyscallHandler {
-    pub fn handle(
-        syscall_num: usize,
-        arg1: usize,
-        arg2: usize,
-        arg3: usize,
-        syscalls: &mut dyn Syscalls,
-    ) -> usize {
+    /// Valor de RAX al volver: el resultado, o -errno si falló
+    pub fn handle(ctx: &SyscallContext, syscalls: &mut dyn Syscalls, abi: Abi) -> usize {
+        let result = if crate::process::is_traced() {
+            Self::dispatch_traced(ctx, syscalls, abi)
+        } else {
+            Self::dispatch(ctx, syscalls, abi)
+        };
+        match result {
+            Ok(value) => value,
+            Err(errno) => errno.as_return(),
+        }
+    }
+    
+    /// `dispatch` escribiendo la llamada y su resultado en la consola
+    fn dispatch_traced(ctx: &SyscallContext, syscalls: &mut dyn Syscalls, abi: Abi) -> SyscallResult {
+        let num = ctx.rax as usize;
+        let pid = crate::process::current_pid();
+        // La llamada se formatea dos veces: si no vuelve, no puede quedar
+        // una cadena suya sin liberar
+        if trace::may_not_return(num) {
+            crate::println!("[{}] {} ...", pid, trace::format_call(ctx));
+        }
+        let result = Self::dispatch(ctx, syscalls, abi);
+        crate::println!("[{}] {} = {}", pid, trace::format_call(ctx), trace::format_result(num, &result));
+        result
+    }
+    
+    fn dispatch(ctx: &SyscallContext, syscalls: &mut dyn Syscalls, abi: Abi) -> SyscallResult {
+        let syscall_num = ctx.rax as usize;
+        let arg1 = ctx.rdi as usize;
+        let arg2 = ctx.rsi as usize;
+        let arg3 = ctx.rdx as usize;
+        
         match syscall_num {
-            SYS_WRITE => {
+            SYS_READ => {
                 let fd = arg1 as u32;
-                let buf_ptr = arg2 as *const u8;
-                let count = arg3;
+                // Una lectura corta es válida: como mucho un trozo por llamada
+                let mut buf = vec![0u8; arg3.min(IO_CHUNK)];
                 
-                let buf = unsafe { core::slice::from_raw_parts(buf_ptr, count) };
-                syscalls.write(fd, buf) as usize
+                let count = syscalls.read(fd, &mut buf)?;
+                copy_to_user(arg2 as u64, &buf[..count])?;
+                Ok(count)
             }
             
-            SYS_READ => {
+            SYS_WRITE => Self::write_user(syscalls, arg1 as u32, arg2 as u64, arg3),
+            
+            SYS_WRITEV => {
                 let fd = arg1 as u32;
-                let buf_ptr = arg2 as *mut u8;
-                let count = arg3;
+                if arg3 > IOV_MAX {
+                    return Err(Errno::EINVAL);
+                }
                 
-                let buf = unsafe { core::slice::from_raw_parts_mut(buf_ptr, count) };
-                syscalls.read(fd, buf) as usize
+                // Cada `struct iovec` es un puntero y una longitud
+                let word = abi.word_size();
+                let mut written = 0;
+                for i in 0..arg3 {
+                    let iov = (arg2 as u64).wrapping_add((i * 2 * word) as u64);
+                    let result = read_user_word(iov, word)
+                        .and_then(|base| Ok((base, read_user_word(iov.wrapping_add(word as u64), word)? as usize)))
+                        .and_then(|(base, len)| {
+                            Self::write_user(syscalls, fd, base, len).map(|count| (count, len))
+                        });
+                    match result {
+                        Ok((count, len)) => {
+                            written += count;
+                            if count < len {
+                                break;
+                            }
+                        }
+                        Err(errno) if written == 0 => return Err(errno),
+                        Err(_) => break,
+                    }
+                }
+                Ok(written)
+            }
+            
+            SYS_OPEN => {
+                let path = read_user_str(arg1 as u64, PATH_MAX)?;
+                syscalls.open(&path, arg2 as u32)
             }
             
-            SYS_EXIT => {
+            SYS_CLOSE => syscalls.close(arg1 as u32),
+            
+            // Sin hilos, salir del grupo es salir del proceso
+            SYS_EXIT | SYS_EXIT_GROUP => {
                 let code = arg1 as i32;
                 syscalls.exit(code);
             }
             
+            SYS_FORK => syscalls.fork(ctx),
+            
+            SYS_EXECVE => {
+                let path = read_user_str(arg1 as u64, PATH_MAX)?;
+                let argv = read_user_str_array(arg2 as u64, abi.word_size(), MAX_ARGS, PATH_MAX)?;
+                let envp = read_user_str_array(arg3 as u64, abi.word_size(), MAX_ARGS, PATH_MAX)?;
+                syscalls.execve(&path, &argv, &envp)
+            }
+            
+            SYS_WAIT4 => {
+                // rusage (arg4) no se soporta
+                syscalls.wait4(arg1 as i64, arg2, arg3 as u32)
+            }
+            
+            SYS_BRK => syscalls.brk(arg1 as u64),
+            
+            SYS_MMAP => syscalls.mmap(arg1 as u64, arg2 as u64, arg3 as u64, ctx.r10, ctx.r8 as i64, ctx.r9),
+            
+            SYS_MUNMAP => syscalls.munmap(arg1 as u64, arg2 as u64),
+            
+            SYS_ARCH_PRCTL => syscalls.arch_prctl(arg1 as u64, arg2 as u64),
+            
+            SYS_SET_TID_ADDRESS => syscalls.set_tid_address(arg1 as u64),
+            
+            SYS_IOCTL => syscalls.ioctl(arg1 as u32, arg2 as u64, arg3 as u64),
+            
+            SYS_GETPID => syscalls.getpid(),
+            
+            SYS_GETPPID => syscalls.getppid(),
+            
+            // Todo se ejecuta como root
+            SYS_GETUID | SYS_GETGID | SYS_GETEUID | SYS_GETEGID => Ok(0),
+            
+            // Todavía no hay señales: se aceptan los cambios de manejadores y
+            // de máscara para que la libc pueda arrancar
+            SYS_RT_SIGACTION | SYS_RT_SIGPROCMASK => Ok(0),
+            
             _ => {
                 crate::println!("Syscall {} no implementada", syscall_num);
-                !0 // -1 en usize
+                Err(Errno::ENOSYS)
+            }
+        }
+    }
+    
+    /// Escribe en `fd` `len` bytes desde `src` (memoria del programa), por
+    /// trozos. Si falla a medias, devuelve lo que ya se escribió.
+    fn write_user(syscalls: &mut dyn Syscalls, fd: u32, src: u64, len: usize) -> SyscallResult {
+        let mut buf = vec![0u8; len.min(IO_CHUNK)];
+        
+        let mut written = 0;
+        while written < len {
+            let chunk = (len - written).min(IO_CHUNK);
+            let result = copy_from_user(&mut buf[..chunk], src.wrapping_add(written as u64))
+                .and_then(|()| syscalls.write(fd, &buf[..chunk]));
+            match result {
+                Ok(count) => {
+                    written += count;
+                    if count < chunk {
+                        break;
+                    }
+                }
+                Err(errno) if written == 0 => return Err(errno),
+                Err(_) => break,
             }
         }
+        Ok(written)
     }
 }
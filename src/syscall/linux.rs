@@ -0,0 +1,29 @@
+//! Constantes del ABI de Linux que usan los argumentos de las syscalls
+
+// Protección de `mmap`
+pub const PROT_READ: u64 = 0x1;
+pub const PROT_WRITE: u64 = 0x2;
+pub const PROT_EXEC: u64 = 0x4;
+
+// Flags de `mmap`
+pub const MAP_SHARED: u64 = 0x01;
+pub const MAP_PRIVATE: u64 = 0x02;
+pub const MAP_FIXED: u64 = 0x10;
+pub const MAP_ANONYMOUS: u64 = 0x20;
+
+// Códigos de `arch_prctl`
+pub const ARCH_SET_FS: u64 = 0x1002;
+pub const ARCH_GET_FS: u64 = 0x1003;
+
+// Peticiones de `ioctl` sobre terminales
+pub const TIOCGWINSZ: u64 = 0x5413;
+
+// Opciones de `wait4`
+pub const WNOHANG: u32 = 1;
+
+// Señales con las que el kernel termina un programa que falla
+pub const SIGILL: u8 = 4;
+pub const SIGTRAP: u8 = 5;
+pub const SIGBUS: u8 = 7;
+pub const SIGFPE: u8 = 8;
+pub const SIGSEGV: u8 = 11;
@@ -1,8 +1,65 @@
 // src/syscall/mod.rs
+mod compat;
+mod context;
+mod entry;
+mod errno;
+mod handler;
+mod linux;
+mod numbers;
+mod trace;
+mod user;
+
+pub use compat::handle_int80;
+pub use context::SyscallContext;
+pub use entry::{init, set_kernel_stack};
+pub use errno::{Errno, SyscallResult};
+pub use handler::{Abi, SyscallHandler};
+pub use linux::{SIGBUS, SIGFPE, SIGILL, SIGSEGV, SIGTRAP};
+pub use numbers::*;
+pub use user::{copy_from_user, copy_to_user, read_user_str, read_user_str_array, read_user_word};
+
+use alloc::string::String;
+use x86_64::VirtAddr;
+use x86_64::structures::paging::PageTableFlags;
+use crate::file::File;
+use crate::memory::AddressSpace;
+use linux::*;
+
 
 pub trait Syscalls {
-    fn write(&mut self, fd: u32, buf: &[u8]) -> i32;
+    /// Puede bloquear hasta que haya datos (stdin espera a una línea)
+    fn read(&mut self, fd: u32, buf: &mut [u8]) -> SyscallResult;
+    fn write(&mut self, fd: u32, buf: &[u8]) -> SyscallResult;
+    fn open(&mut self, path: &str, flags: u32) -> SyscallResult;
+    fn close(&mut self, fd: u32) -> SyscallResult;
     fn exit(&mut self, code: i32) -> !;
+    /// Devuelve el pid del hijo al padre (el hijo vuelve con 0)
+    fn fork(&mut self, ctx: &SyscallContext) -> SyscallResult;
+    /// Deja el programa cargado; empieza al salir de la syscall (ver
+    /// `process::finish_exec`)
+    fn execve(&mut self, path: &str, argv: &[String], envp: &[String]) -> SyscallResult;
+    /// `pid` -1 espera a cualquier hijo; el estado se escribe en `wstatus`
+    /// (dirección de usuario, 0 si no interesa) con el formato de Linux.
+    /// Con WNOHANG devuelve 0 en vez de esperar.
+    fn wait4(&mut self, pid: i64, wstatus: usize, options: u32) -> SyscallResult;
+    /// Mueve el final del heap; devuelve el final resultante (con `addr` 0,
+    /// el actual)
+    fn brk(&mut self, addr: u64) -> SyscallResult;
+    /// Solo mapeos privados: anónimos o de un programa abierto con `open`
+    /// (que se copia al mapearlo, a partir de `offset`)
+    fn mmap(&mut self, addr: u64, len: u64, prot: u64, flags: u64, fd: i64, offset: u64) -> SyscallResult;
+    fn munmap(&mut self, addr: u64, len: u64) -> SyscallResult;
+    /// `ARCH_SET_FS`/`ARCH_GET_FS`: el puntero de hilo de la libc
+    fn arch_prctl(&mut self, code: u64, addr: u64) -> SyscallResult;
+    fn set_tid_address(&mut self, tidptr: u64) -> SyscallResult;
+    fn ioctl(&mut self, fd: u32, request: u64, arg: u64) -> SyscallResult;
+    fn getpid(&mut self) -> SyscallResult;
+    fn getppid(&mut self) -> SyscallResult;
+}
+
+/// Ejecuta `f` con el espacio de direcciones del proceso actual
+fn with_space<R>(f: impl FnOnce(&mut AddressSpace) -> R) -> Result<R, Errno> {
+    crate::process::with_current_space(f).map_err(|_| Errno::EFAULT)
 }
 
 pub struct KernelSyscalls;
@@ -14,27 +71,180 @@ impl KernelSyscalls {
 }
 
 impl Syscalls for KernelSyscalls {
-    fn write(&mut self, fd: u32, buf: &[u8]) -> i32 {
-        match fd {
-            1 | 2 => { // stdout/stderr
-                if let Ok(s) = core::str::from_utf8(buf) {
-                    crate::print!("{}", s);
-                }
-                buf.len() as i32
+    fn read(&mut self, fd: u32, buf: &mut [u8]) -> SyscallResult {
+        // Sin la tabla de procesos cogida: leer de la consola bloquea
+        crate::process::file(fd).ok_or(Errno::EBADF)?.read(buf)
+    }
+    
+    fn write(&mut self, fd: u32, buf: &[u8]) -> SyscallResult {
+        crate::process::file(fd).ok_or(Errno::EBADF)?.write(buf)
+    }
+    
+    fn open(&mut self, path: &str, flags: u32) -> SyscallResult {
+        let file = crate::file::open(path, flags)?;
+        crate::process::open_file(file).map(|fd| fd as usize)
+    }
+    
+    fn close(&mut self, fd: u32) -> SyscallResult {
+        crate::process::close_file(fd).map(|()| 0)
+    }
+    
+    fn exit(&mut self, code: i32) -> ! {
+        crate::println!("Programa terminado con código: {}", code);
+        crate::process::exit(code)
+    }
+    
+    fn fork(&mut self, ctx: &SyscallContext) -> SyscallResult {
+        crate::process::fork(ctx).map(|pid| pid as usize)
+    }
+    
+    fn execve(&mut self, path: &str, argv: &[String], envp: &[String]) -> SyscallResult {
+        crate::process::exec(path, argv, envp)?;
+        Ok(0)
+    }
+    
+    fn wait4(&mut self, pid: i64, wstatus: usize, options: u32) -> SyscallResult {
+        if options & !WNOHANG != 0 {
+            return Err(Errno::EINVAL);
+        }
+        let target = if pid > 0 { Some(pid as u64) } else { None };
+        let (child, status) = if options & WNOHANG != 0 {
+            match crate::process::try_waitpid(target)? {
+                Some(child) => child,
+                None => return Ok(0),
+            }
+        } else {
+            crate::process::waitpid(target)?
+        };
+        if wstatus != 0 {
+            copy_to_user(wstatus as u64, &status.wait_status().to_ne_bytes())?;
+        }
+        Ok(child as usize)
+    }
+    
+    fn brk(&mut self, addr: u64) -> SyscallResult {
+        with_space(|space| space.set_brk(addr) as usize)
+    }
+    
+    fn mmap(&mut self, addr: u64, len: u64, prot: u64, flags: u64, fd: i64, offset: u64) -> SyscallResult {
+        // Los mapeos compartidos necesitarían un caché de páginas
+        if flags & (MAP_SHARED | MAP_PRIVATE) != MAP_PRIVATE {
+            return Err(Errno::ENODEV);
+        }
+        if len == 0 {
+            return Err(Errno::EINVAL);
+        }
+        // Lo que se copia del archivo: desde `offset`, como mucho `len` bytes.
+        // Lo que quede del mapeo después del final del archivo va a cero.
+        let contents = if flags & MAP_ANONYMOUS == 0 {
+            if offset % 4096 != 0 {
+                return Err(Errno::EINVAL);
             }
-            _ => {
-                crate::println!("write a fd {} no implementado", fd);
-                -1
+            let fd = u32::try_from(fd).map_err(|_| Errno::EBADF)?;
+            let data = crate::process::file(fd).ok_or(Errno::EBADF)?.contents().ok_or(Errno::ENODEV)?;
+            let data = usize::try_from(offset).ok().and_then(|offset| data.get(offset..)).unwrap_or(&[]);
+            &data[..data.len().min(len as usize)]
+        } else {
+            &[]
+        };
+        if prot & PROT_WRITE != 0 && prot & PROT_EXEC != 0 {
+            return Err(Errno::EACCES);
+        }
+        
+        // Sin PROT_READ ni nada más (PROT_NONE) las páginas no son
+        // accesibles desde ring 3
+        let mut page_flags = PageTableFlags::PRESENT;
+        if prot & (PROT_READ | PROT_WRITE | PROT_EXEC) != 0 {
+            page_flags |= PageTableFlags::USER_ACCESSIBLE;
+        }
+        if prot & PROT_WRITE != 0 {
+            page_flags |= PageTableFlags::WRITABLE;
+        }
+        if prot & PROT_EXEC == 0 {
+            page_flags |= PageTableFlags::NO_EXECUTE;
+        }
+        
+        let fixed = if flags & MAP_FIXED != 0 {
+            let addr = VirtAddr::try_new(addr).map_err(|_| Errno::EINVAL)?;
+            if !addr.is_aligned(4096u64) {
+                return Err(Errno::EINVAL);
+            }
+            Some(addr)
+        } else {
+            None
+        };
+        let start = with_space(|space| space.map_anonymous(fixed, len, page_flags))?
+            .map_err(|_| if fixed.is_some() { Errno::EINVAL } else { Errno::ENOMEM })?;
+        // Página a página, para no tener la tabla de procesos cogida (y las
+        // interrupciones desactivadas) durante toda la copia. `write` respalda
+        // las páginas sin mirar los permisos del mapeo.
+        for (i, chunk) in contents.chunks(4096).enumerate() {
+            if with_space(|space| space.write(start + i as u64 * 4096, chunk))?.is_err() {
+                let _ = with_space(|space| space.unmap_user(start, len));
+                return Err(Errno::ENOMEM);
             }
         }
+        Ok(start.as_u64() as usize)
     }
     
-    fn exit(&mut self, code: i32) -> ! {
-        crate::println!("Programa terminado con código: {}", code);
-        // Por ahora, volvemos al kernel (esto debería ser un salto real)
-        // Pero como es !, no podemos volver. Así que hacemos un loop.
-        loop {
-            x86_64::instructions::hlt();
+    fn munmap(&mut self, addr: u64, len: u64) -> SyscallResult {
+        let addr = VirtAddr::try_new(addr).map_err(|_| Errno::EINVAL)?;
+        if len == 0 {
+            return Err(Errno::EINVAL);
+        }
+        with_space(|space| space.unmap_user(addr, len))?
+            .map(|()| 0)
+            .map_err(|_| Errno::EINVAL)
+    }
+    
+    fn arch_prctl(&mut self, code: u64, addr: u64) -> SyscallResult {
+        match code {
+            ARCH_SET_FS => {
+                // La base de FS tiene que ser una dirección de la mitad baja
+                let base = VirtAddr::try_new(addr)
+                    .ok()
+                    .filter(|base| base.as_u64() < crate::usermode::USER_SPACE_END)
+                    .ok_or(Errno::EPERM)?;
+                crate::task::set_fs_base(base);
+                Ok(0)
+            }
+            ARCH_GET_FS => {
+                let base = crate::task::fs_base().as_u64();
+                copy_to_user(addr, &base.to_ne_bytes())?;
+                Ok(0)
+            }
+            _ => Err(Errno::EINVAL),
         }
     }
+    
+    fn set_tid_address(&mut self, _tidptr: u64) -> SyscallResult {
+        // Sin hilos no hay nadie a quien avisar al salir: basta con el tid,
+        // que es el pid
+        self.getpid()
+    }
+    
+    fn ioctl(&mut self, fd: u32, request: u64, arg: u64) -> SyscallResult {
+        let file = crate::process::file(fd).ok_or(Errno::EBADF)?;
+        match (&*file, request) {
+            (File::Console, TIOCGWINSZ) => {
+                // struct winsize: filas, columnas y tamaño en píxeles (sin usar)
+                let (cols, rows) = crate::framebuffer::text_size();
+                let winsize: alloc::vec::Vec<u8> = [rows as u16, cols as u16, 0, 0]
+                    .iter()
+                    .flat_map(|value| value.to_ne_bytes())
+                    .collect();
+                copy_to_user(arg, &winsize)?;
+                Ok(0)
+            }
+            _ => Err(Errno::ENOTTY),
+        }
+    }
+    
+    fn getpid(&mut self) -> SyscallResult {
+        Ok(crate::process::current_pid() as usize)
+    }
+    
+    fn getppid(&mut self) -> SyscallResult {
+        Ok(crate::process::parent_pid() as usize)
+    }
 }
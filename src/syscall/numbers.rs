@@ -4,5 +4,56 @@ pub const SYS_READ: usize = 0;
 pub const SYS_WRITE: usize = 1;
 pub const SYS_OPEN: usize = 2;
 pub const SYS_CLOSE: usize = 3;
+pub const SYS_MMAP: usize = 9;
+pub const SYS_MUNMAP: usize = 11;
+pub const SYS_BRK: usize = 12;
+pub const SYS_RT_SIGACTION: usize = 13;
+pub const SYS_RT_SIGPROCMASK: usize = 14;
+pub const SYS_IOCTL: usize = 16;
+pub const SYS_WRITEV: usize = 20;
+pub const SYS_GETPID: usize = 39;
+pub const SYS_FORK: usize = 57;
+pub const SYS_EXECVE: usize = 59;
 pub const SYS_EXIT: usize = 60;
-// Añade más según necesites
+pub const SYS_WAIT4: usize = 61;
+pub const SYS_GETUID: usize = 102;
+pub const SYS_GETGID: usize = 104;
+pub const SYS_GETEUID: usize = 107;
+pub const SYS_GETEGID: usize = 108;
+pub const SYS_GETPPID: usize = 110;
+pub const SYS_ARCH_PRCTL: usize = 158;
+pub const SYS_SET_TID_ADDRESS: usize = 218;
+pub const SYS_EXIT_GROUP: usize = 231;
+// Añade más según necesites (y su nombre en `name`)
+
+/// Nombre de la syscall `num`, para las trazas
+pub fn name(num: usize) -> Option<&'static str> {
+    let name = match num {
+        SYS_READ => "read",
+        SYS_WRITE => "write",
+        SYS_OPEN => "open",
+        SYS_CLOSE => "close",
+        SYS_MMAP => "mmap",
+        SYS_MUNMAP => "munmap",
+        SYS_BRK => "brk",
+        SYS_RT_SIGACTION => "rt_sigaction",
+        SYS_RT_SIGPROCMASK => "rt_sigprocmask",
+        SYS_IOCTL => "ioctl",
+        SYS_WRITEV => "writev",
+        SYS_GETPID => "getpid",
+        SYS_FORK => "fork",
+        SYS_EXECVE => "execve",
+        SYS_EXIT => "exit",
+        SYS_WAIT4 => "wait4",
+        SYS_GETUID => "getuid",
+        SYS_GETGID => "getgid",
+        SYS_GETEUID => "geteuid",
+        SYS_GETEGID => "getegid",
+        SYS_GETPPID => "getppid",
+        SYS_ARCH_PRCTL => "arch_prctl",
+        SYS_SET_TID_ADDRESS => "set_tid_address",
+        SYS_EXIT_GROUP => "exit_group",
+        _ => return None,
+    };
+    Some(name)
+}
@@ -0,0 +1,98 @@
+//! Traza de syscalls al estilo de strace
+//!
+//! Con la traza activada en un proceso (`trace` en la shell), cada syscall
+//! se escribe en la consola con sus argumentos decodificados y el resultado:
+//!
+//! ```text
+//! [3] open("/dev/console", 0x0) = 3
+//! [3] read(5, 0x7fffffffe000, 64) = -1 EBADF (Descriptor no válido)
+//! ```
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::{numbers, read_user_str, Errno, SyscallContext, SyscallResult};
+use super::{SYS_READ, SYS_WRITE, SYS_OPEN, SYS_CLOSE, SYS_EXIT, SYS_WAIT4, SYS_EXECVE};
+use super::{SYS_MMAP, SYS_MUNMAP, SYS_BRK, SYS_RT_SIGACTION, SYS_RT_SIGPROCMASK, SYS_IOCTL, SYS_WRITEV};
+use super::{SYS_ARCH_PRCTL, SYS_SET_TID_ADDRESS, SYS_EXIT_GROUP};
+
+// Longitud máxima de las cadenas que se muestran
+const MAX_STR: usize = 64;
+
+/// Cómo mostrar un argumento
+#[derive(Clone, Copy)]
+enum Arg {
+    /// Entero con signo
+    Int,
+    /// Descriptor de archivo
+    Fd,
+    /// Dirección o flags, en hexadecimal
+    Hex,
+    /// Cadena terminada en NUL en memoria del programa
+    Str,
+}
+
+use Arg::*;
+
+/// Argumentos de cada syscall conocida
+fn signature(num: usize) -> &'static [Arg] {
+    match num {
+        SYS_READ | SYS_WRITE | SYS_WRITEV => &[Fd, Hex, Int],
+        SYS_OPEN => &[Str, Hex],
+        SYS_CLOSE => &[Fd],
+        SYS_MMAP => &[Hex, Int, Hex, Hex, Fd, Int],
+        SYS_MUNMAP => &[Hex, Int],
+        SYS_BRK | SYS_SET_TID_ADDRESS => &[Hex],
+        SYS_RT_SIGACTION | SYS_RT_SIGPROCMASK => &[Int, Hex, Hex],
+        SYS_IOCTL => &[Fd, Hex, Hex],
+        SYS_EXECVE => &[Str, Hex, Hex],
+        SYS_EXIT | SYS_EXIT_GROUP => &[Int],
+        SYS_WAIT4 => &[Int, Hex, Hex, Hex],
+        SYS_ARCH_PRCTL => &[Hex, Hex],
+        _ => &[],
+    }
+}
+
+/// `true` si la syscall puede no volver (y su llamada se escribe antes)
+pub fn may_not_return(num: usize) -> bool {
+    matches!(num, SYS_EXIT | SYS_EXIT_GROUP | SYS_EXECVE)
+}
+
+fn format_arg(arg: Arg, value: u64) -> String {
+    match arg {
+        Int => format!("{}", value as i64),
+        Fd => format!("{}", value as i32),
+        Hex => format!("{:#x}", value),
+        Str => match read_user_str(value, MAX_STR) {
+            Ok(s) => format!("{:?}", s),
+            Err(Errno::ENAMETOOLONG) => format!("{:#x} (cadena larga)", value),
+            Err(_) => format!("{:#x}", value),
+        },
+    }
+}
+
+/// `nombre(arg1, arg2, ...)` de la syscall de `ctx`. Se llama antes de
+/// ejecutarla: después la memoria de las cadenas puede haber cambiado.
+pub fn format_call(ctx: &SyscallContext) -> String {
+    let num = ctx.rax as usize;
+    let Some(name) = numbers::name(num) else {
+        return format!("syscall_{}({:#x}, {:#x}, {:#x})", num, ctx.rdi, ctx.rsi, ctx.rdx);
+    };
+    let values = [ctx.rdi, ctx.rsi, ctx.rdx, ctx.r10, ctx.r8, ctx.r9];
+    let args: Vec<String> = signature(num)
+        .iter()
+        .zip(values)
+        .map(|(&arg, value)| format_arg(arg, value))
+        .collect();
+    format!("{}({})", name, args.join(", "))
+}
+
+/// Resultado como lo muestra strace: el valor, o -1 con el errno
+pub fn format_result(num: usize, result: &SyscallResult) -> String {
+    match result {
+        // Las direcciones, mejor en hexadecimal
+        Ok(value) if num == SYS_MMAP || num == SYS_BRK => format!("{:#x}", value),
+        Ok(value) => format!("{}", value),
+        Err(errno) => format!("-1 {:?} ({})", errno, errno.description()),
+    }
+}
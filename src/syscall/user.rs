@@ -0,0 +1,78 @@
+//! Acceso a memoria de usuario desde las syscalls
+//!
+//! Nunca se desreferencia un puntero del programa: se recorren las tablas de
+//! páginas de su espacio (solo la mitad baja, con permisos de ring 3) y se
+//! copia por el HHDM. Un puntero inválido se convierte en EFAULT.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use x86_64::VirtAddr;
+use super::Errno;
+
+fn user_addr(addr: u64) -> Result<VirtAddr, Errno> {
+    VirtAddr::try_new(addr).map_err(|_| Errno::EFAULT)
+}
+
+/// Copia `buf.len()` bytes desde `src` (memoria del programa actual)
+pub fn copy_from_user(buf: &mut [u8], src: u64) -> Result<(), Errno> {
+    let src = user_addr(src)?;
+    crate::process::with_current_space(|space| space.copy_from_user(src, buf))
+        .map_err(|_| Errno::EFAULT)?
+        .map_err(|_| Errno::EFAULT)
+}
+
+/// Copia `data` a `dst` (memoria del programa actual)
+pub fn copy_to_user(dst: u64, data: &[u8]) -> Result<(), Errno> {
+    let dst = user_addr(dst)?;
+    crate::process::with_current_space(|space| space.copy_to_user(dst, data))
+        .map_err(|_| Errno::EFAULT)?
+        .map_err(|_| Errno::EFAULT)
+}
+
+/// Lee una cadena terminada en NUL de como mucho `max` bytes (sin el NUL)
+pub fn read_user_str(src: u64, max: usize) -> Result<String, Errno> {
+    let mut bytes = Vec::new();
+    let mut addr = src;
+    while bytes.len() < max {
+        // De página en página: la cadena puede acabar justo antes de una
+        // página sin mapear
+        let chunk = (4096 - (addr as usize & 0xFFF)).min(max - bytes.len());
+        let start = bytes.len();
+        bytes.resize(start + chunk, 0);
+        copy_from_user(&mut bytes[start..], addr)?;
+        if let Some(nul) = bytes[start..].iter().position(|&b| b == 0) {
+            bytes.truncate(start + nul);
+            return String::from_utf8(bytes).map_err(|_| Errno::EINVAL);
+        }
+        addr += chunk as u64;
+    }
+    Err(Errno::ENAMETOOLONG)
+}
+
+/// Lee un puntero o un `long` de `word_size` bytes (8, o 4 en un programa
+/// de 32 bits)
+pub fn read_user_word(src: u64, word_size: usize) -> Result<u64, Errno> {
+    let mut bytes = [0u8; 8];
+    copy_from_user(&mut bytes[..word_size], src)?;
+    Ok(u64::from_ne_bytes(bytes))
+}
+
+/// Lee un array de punteros a cadenas terminado en NULL (como `argv` de
+/// `execve`), con como mucho `max_count` cadenas. `src` 0 es un array vacío.
+/// Los punteros ocupan `word_size` bytes (4 en un programa de 32 bits).
+pub fn read_user_str_array(src: u64, word_size: usize, max_count: usize, max_len: usize) -> Result<Vec<String>, Errno> {
+    let mut strings = Vec::new();
+    if src == 0 {
+        return Ok(strings);
+    }
+    loop {
+        if strings.len() >= max_count {
+            return Err(Errno::E2BIG);
+        }
+        let ptr = read_user_word(src.wrapping_add((strings.len() * word_size) as u64), word_size)?;
+        if ptr == 0 {
+            return Ok(strings);
+        }
+        strings.push(read_user_str(ptr, max_len)?);
+    }
+}
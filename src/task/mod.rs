@@ -0,0 +1,337 @@
+// src/task/mod.rs
+//! Tareas del kernel y planificador round-robin expropiativo
+//!
+//! Cada tarea tiene su propia pila de kernel; los programas de usuario son
+//! tareas que, tras cargar el ELF, saltan a ring 3 y vuelven a su pila de
+//! kernel en cada interrupción o syscall. El espacio de direcciones es del
+//! proceso (ver `process`); la tarea solo guarda su PML4 para activarla. El temporizador (ver
+//! `interrupts::timer`) llama a `on_tick`, que despierta a las tareas dormidas
+//! y cambia de tarea en cada tick si hay alguna otra lista.
+
+mod switch;
+
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use x86_64::instructions::interrupts;
+use x86_64::registers::model_specific::FsBase;
+use x86_64::structures::paging::PhysFrame;
+use x86_64::VirtAddr;
+use crate::memory::AddressSpace;
+use crate::sync::IrqMutex;
+use switch::{save_fx, switch_context, FxArea, SAVED_REGISTERS};
+
+pub type TaskId = u64;
+
+const KERNEL_STACK_SIZE: usize = 16 * 1024;
+
+// Bit 1 de RFLAGS (reservado, siempre a 1); IF lo activa `task_start`
+const INITIAL_RFLAGS: u64 = 0x2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    Ready,
+    Running,
+    /// Dormida hasta el tick indicado
+    Sleeping(u64),
+    /// Esperando a que otra tarea la despierte con `wake`
+    Blocked,
+    Exited,
+}
+
+struct Task {
+    id: TaskId,
+    state: TaskState,
+    /// RSP guardado por `switch_context` mientras la tarea no se ejecuta
+    rsp: u64,
+    /// `None` para la tarea de arranque, que usa la pila de Limine
+    kernel_stack: Option<Box<[u8]>>,
+    fx: Box<FxArea>,
+    entry: Option<Box<dyn FnOnce() + Send>>,
+    /// Lo que se ejecuta cuando vuelve `entry`, ya liberada; no vuelve
+    then: fn() -> !,
+    /// PML4 del proceso al que pertenece; `None` para usar la del kernel
+    page_table: Option<PhysFrame>,
+    /// Base del segmento FS en ring 3 (el puntero de hilo de la libc)
+    fs_base: u64,
+}
+
+impl Task {
+    fn stack_top(&self) -> Option<u64> {
+        self.kernel_stack.as_ref().map(|stack| {
+            VirtAddr::new(stack.as_ptr() as u64 + stack.len() as u64).align_down(16u64).as_u64()
+        })
+    }
+}
+
+struct Scheduler {
+    tasks: BTreeMap<TaskId, Box<Task>>,
+    ready: VecDeque<TaskId>,
+    current: TaskId,
+    idle: TaskId,
+    next_id: TaskId,
+}
+
+static SCHEDULER: IrqMutex<Option<Scheduler>> = IrqMutex::new(None);
+
+/// Convierte el contexto de arranque en la tarea 0 ("kernel") y crea la
+/// tarea ociosa, que solo se ejecuta cuando no hay ninguna otra lista.
+pub fn init() {
+    let boot = Box::new(Task {
+        id: 0,
+        state: TaskState::Running,
+        rsp: 0,
+        kernel_stack: None,
+        fx: Box::new(FxArea::new()),
+        entry: None,
+        then: exit,
+        page_table: None,
+        fs_base: 0,
+    });
+
+    let mut tasks = BTreeMap::new();
+    tasks.insert(0, boot);
+    *SCHEDULER.lock() = Some(Scheduler { tasks, ready: VecDeque::new(), current: 0, idle: 0, next_id: 1 });
+
+    let idle = create(|| loop {
+        interrupts::enable_and_hlt();
+    }, exit);
+    with_scheduler(|scheduler| scheduler.idle = idle);
+}
+
+fn with_scheduler<R>(f: impl FnOnce(&mut Scheduler) -> R) -> R {
+    let mut scheduler = SCHEDULER.lock();
+    f(scheduler.as_mut().expect("Planificador no inicializado"))
+}
+
+// Crea la tarea sin ponerla en la cola
+fn create(entry: impl FnOnce() + Send + 'static, then: fn() -> !) -> TaskId {
+    let stack = alloc::vec![0u8; KERNEL_STACK_SIZE].into_boxed_slice();
+    let mut task = Box::new(Task {
+        id: 0,
+        state: TaskState::Ready,
+        rsp: 0,
+        kernel_stack: Some(stack),
+        fx: Box::new(FxArea::new()),
+        entry: Some(Box::new(entry)),
+        then,
+        page_table: None,
+        fs_base: 0,
+    });
+
+    // Lo que `switch_context` espera encontrar: los registros guardados (a
+    // cero), RFLAGS y la dirección de retorno. La palabra extra deja la pila
+    // alineada como tras un `call` al entrar en `task_start`.
+    let top = task.stack_top().unwrap();
+    let rsp = top - 8 * (SAVED_REGISTERS as u64 + 2);
+    unsafe {
+        let frame = rsp as *mut u64;
+        for i in 0..SAVED_REGISTERS - 1 {
+            frame.add(i).write(0);
+        }
+        frame.add(SAVED_REGISTERS - 1).write(INITIAL_RFLAGS);
+        frame.add(SAVED_REGISTERS).write(task_start as usize as u64);
+        frame.add(SAVED_REGISTERS + 1).write(0);
+    }
+    task.rsp = rsp;
+
+    with_scheduler(|scheduler| {
+        let id = scheduler.next_id;
+        scheduler.next_id += 1;
+        task.id = id;
+        scheduler.tasks.insert(id, task);
+        id
+    })
+}
+
+/// Crea una tarea del kernel que ejecutará `entry` y la pone en la cola.
+/// Cuando `entry` vuelve, ya liberada, la tarea sigue en `then` (`exit` para
+/// terminar). Lo que no vuelve (saltar a ring 3, salir) tiene que hacerse
+/// ahí: dentro de `entry` dejaría sin liberar todo lo que esta tenga.
+pub fn spawn(entry: impl FnOnce() + Send + 'static, then: fn() -> !) -> TaskId {
+    let id = create(entry, then);
+    with_scheduler(|scheduler| scheduler.ready.push_back(id));
+    id
+}
+
+// Primera función de toda tarea nueva (llega aquí con `ret` desde `switch_context`)
+extern "C" fn task_start() -> ! {
+    let (entry, then) = with_scheduler(|scheduler| {
+        let current = scheduler.current;
+        let task = scheduler.tasks.get_mut(&current).expect("Tarea actual inexistente");
+        (task.entry.take(), task.then)
+    });
+    interrupts::enable();
+    if let Some(entry) = entry {
+        entry();
+    }
+    then()
+}
+
+pub fn current_id() -> TaskId {
+    with_scheduler(|scheduler| scheduler.current)
+}
+
+/// Cede el CPU a la siguiente tarea lista
+pub fn yield_now() {
+    schedule();
+}
+
+/// Duerme la tarea actual al menos `ms` milisegundos
+pub fn sleep_ms(ms: u64) {
+    let wake_at = crate::interrupts::ticks() + crate::interrupts::ms_to_ticks(ms).max(1);
+    with_scheduler(|scheduler| {
+        let current = scheduler.current;
+        if let Some(task) = scheduler.tasks.get_mut(&current) {
+            task.state = TaskState::Sleeping(wake_at);
+        }
+    });
+    schedule();
+}
+
+/// Marca la tarea actual como bloqueada; deja de ejecutarse en el próximo
+/// `yield_now`. Separarlo del cambio de tarea permite marcarla con otro lock
+/// cogido y soltarlo antes de ceder sin perder un `wake` intermedio.
+pub fn mark_blocked() {
+    with_scheduler(|scheduler| {
+        let current = scheduler.current;
+        if let Some(task) = scheduler.tasks.get_mut(&current) {
+            task.state = TaskState::Blocked;
+        }
+    });
+}
+
+/// Vuelve a poner en la cola una tarea bloqueada
+pub fn wake(id: TaskId) {
+    with_scheduler(|scheduler| {
+        if let Some(task) = scheduler.tasks.get_mut(&id) {
+            if task.state == TaskState::Blocked {
+                task.state = TaskState::Ready;
+                scheduler.ready.push_back(id);
+            }
+        }
+    });
+}
+
+/// Termina la tarea actual. Su pila se libera cuando ya no se esté usando.
+pub fn exit() -> ! {
+    with_scheduler(|scheduler| {
+        let current = scheduler.current;
+        let task = scheduler.tasks.get_mut(&current).expect("Tarea actual inexistente");
+        task.state = TaskState::Exited;
+        task.page_table = None;
+    });
+    AddressSpace::activate_kernel();
+    schedule();
+    unreachable!("Una tarea terminada volvió a ejecutarse");
+}
+
+/// Llamada en cada tick del temporizador
+pub fn on_tick() {
+    let now = crate::interrupts::ticks();
+    let switch = {
+        let mut guard = SCHEDULER.lock();
+        let Some(scheduler) = guard.as_mut() else { return };
+        for task in scheduler.tasks.values_mut() {
+            if let TaskState::Sleeping(wake_at) = task.state {
+                if wake_at <= now {
+                    task.state = TaskState::Ready;
+                    scheduler.ready.push_back(task.id);
+                }
+            }
+        }
+        !scheduler.ready.is_empty()
+    };
+    if switch {
+        schedule();
+    }
+}
+
+/// Elige la siguiente tarea y cambia a ella
+fn schedule() {
+    interrupts::without_interrupts(|| {
+        let (old_rsp, new_rsp, old_fx, new_fx) = {
+            let mut guard = SCHEDULER.lock();
+            let scheduler = guard.as_mut().expect("Planificador no inicializado");
+
+            // Las tareas terminadas que no son la actual ya no usan su pila
+            let current = scheduler.current;
+            scheduler.tasks.retain(|&id, task| id == current || task.state != TaskState::Exited);
+
+            let current_task = scheduler.tasks.get_mut(&current).unwrap();
+            let still_running = current_task.state == TaskState::Running;
+            let next = match scheduler.ready.pop_front() {
+                Some(next) => next,
+                None if still_running => return,
+                None => scheduler.idle,
+            };
+
+            if still_running {
+                current_task.state = TaskState::Ready;
+                if current != scheduler.idle {
+                    scheduler.ready.push_back(current);
+                }
+            }
+            if next == current {
+                current_task.state = TaskState::Running;
+                return;
+            }
+            let old_rsp = &mut current_task.rsp as *mut u64;
+            let old_fx = &mut *current_task.fx as *mut FxArea;
+
+            let next_task = scheduler.tasks.get_mut(&next).unwrap();
+            next_task.state = TaskState::Running;
+            if let Some(top) = next_task.stack_top() {
+                crate::gdt::set_kernel_stack(VirtAddr::new(top));
+                crate::syscall::set_kernel_stack(top);
+            }
+            AddressSpace::activate_table(next_task.page_table);
+            FsBase::write(VirtAddr::new(next_task.fs_base));
+            scheduler.current = next;
+
+            (old_rsp, next_task.rsp, old_fx, &*next_task.fx as *const FxArea)
+        };
+
+        // Las tareas están en `Box`, así que los punteros siguen siendo
+        // válidos tras soltar el lock (la actual no se libera mientras corre).
+        unsafe { switch_context(old_rsp, new_rsp, old_fx, new_fx) };
+    });
+}
+
+/// Copia a `child` el estado x87/SSE actual, para que el hijo de un `fork`
+/// herede los registros del programa que lo llamó
+pub fn inherit_fx_state(child: TaskId) {
+    with_scheduler(|scheduler| {
+        if let Some(task) = scheduler.tasks.get_mut(&child) {
+            save_fx(&mut task.fx);
+        }
+    });
+}
+
+/// Cambia la PML4 de la tarea actual y la activa
+pub fn set_page_table(pml4: Option<PhysFrame>) {
+    with_scheduler(|scheduler| {
+        let current = scheduler.current;
+        if let Some(task) = scheduler.tasks.get_mut(&current) {
+            task.page_table = pml4;
+        }
+        AddressSpace::activate_table(pml4);
+    });
+}
+
+/// Cambia la base de FS de la tarea actual (`arch_prctl(ARCH_SET_FS)`)
+pub fn set_fs_base(base: VirtAddr) {
+    with_scheduler(|scheduler| {
+        let current = scheduler.current;
+        if let Some(task) = scheduler.tasks.get_mut(&current) {
+            task.fs_base = base.as_u64();
+        }
+        FsBase::write(base);
+    });
+}
+
+pub fn fs_base() -> VirtAddr {
+    with_scheduler(|scheduler| {
+        let base = scheduler.tasks.get(&scheduler.current).map_or(0, |task| task.fs_base);
+        VirtAddr::new(base)
+    })
+}
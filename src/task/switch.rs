@@ -0,0 +1,59 @@
+//! Cambio de contexto entre tareas del kernel
+
+use core::arch::{asm, global_asm};
+
+/// Tamaño del área de FXSAVE (estado x87/SSE)
+pub const FX_AREA_SIZE: usize = 512;
+
+/// Estado x87/SSE de una tarea (FXSAVE exige alineación a 16)
+#[repr(C, align(16))]
+pub struct FxArea(pub [u8; FX_AREA_SIZE]);
+
+impl FxArea {
+    /// Estado inicial equivalente a `fninit` con MXCSR por defecto
+    pub fn new() -> Self {
+        let mut area = [0u8; FX_AREA_SIZE];
+        area[0..2].copy_from_slice(&0x037Fu16.to_le_bytes()); // FCW
+        area[24..28].copy_from_slice(&0x1F80u32.to_le_bytes()); // MXCSR
+        FxArea(area)
+    }
+}
+
+/// Guarda en `area` el estado x87/SSE que hay ahora en el CPU
+pub fn save_fx(area: &mut FxArea) {
+    unsafe { asm!("fxsave64 [{}]", in(reg) area as *mut FxArea, options(nostack)) };
+}
+
+extern "C" {
+    /// Guarda los registros callee-saved, RFLAGS y el estado SSE de la tarea
+    /// actual, deja su RSP en `*old_rsp` y continúa en `new_rsp`.
+    pub fn switch_context(old_rsp: *mut u64, new_rsp: u64, old_fx: *mut FxArea, new_fx: *const FxArea);
+}
+
+/// Número de palabras que `switch_context` saca de la pila al reanudar
+/// (r15, r14, r13, r12, rbx, rbp, rflags)
+pub const SAVED_REGISTERS: usize = 7;
+
+global_asm!(
+    ".global switch_context",
+    "switch_context:",
+    "    fxsave64 [rdx]",
+    "    pushfq",
+    "    push rbp",
+    "    push rbx",
+    "    push r12",
+    "    push r13",
+    "    push r14",
+    "    push r15",
+    "    mov [rdi], rsp",
+    "    mov rsp, rsi",
+    "    pop r15",
+    "    pop r14",
+    "    pop r13",
+    "    pop r12",
+    "    pop rbx",
+    "    pop rbp",
+    "    popfq",
+    "    fxrstor64 [rcx]",
+    "    ret",
+);
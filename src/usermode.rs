@@ -0,0 +1,181 @@
+// src/usermode.rs
+//! Paso a ring 3 para los programas cargados por `ElfLoader`
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::arch::asm;
+use x86_64::instructions::random::RdRand;
+use x86_64::{VirtAddr, structures::paging::PageTableFlags};
+use crate::elf::{Class, ElfError};
+use crate::memory::{AddressSpace, RegionKind};
+use crate::syscall::SyscallContext;
+
+/// Todo lo que esté por debajo pertenece al programa
+pub const USER_SPACE_END: u64 = 0x0000_8000_0000_0000;
+
+/// Cima de la pila de usuario; la página de debajo de la pila queda sin
+/// mapear para que un desbordamiento provoque un fallo en vez de pisar datos.
+pub const USER_STACK_TOP: u64 = 0x0000_7fff_ffff_f000;
+pub const USER_STACK_PAGES: u64 = 256;
+
+/// Límite de lo que ve un programa de 32 bits en modo compatibilidad
+pub const COMPAT_SPACE_END: u64 = 0x1_0000_0000;
+
+/// Cima de la pila de un programa de 32 bits, con su página de guarda encima
+pub const COMPAT_STACK_TOP: u64 = 0xffff_f000;
+
+// IF activado + bit 1 (reservado, siempre a 1)
+const USER_RFLAGS: u64 = 0x202;
+
+/// Reserva en `space` la pila de usuario que acaba en `top` y devuelve su
+/// cima. Las páginas se mapean al tocarlas (ver `AddressSpace::handle_page_fault`).
+pub fn map_user_stack(space: &mut AddressSpace, top: u64) -> Result<u64, &'static str> {
+    let bottom = top - USER_STACK_PAGES * 4096;
+    let flags = PageTableFlags::PRESENT
+        | PageTableFlags::WRITABLE
+        | PageTableFlags::USER_ACCESSIBLE
+        | PageTableFlags::NO_EXECUTE;
+    space.add_lazy_region(VirtAddr::new(bottom), VirtAddr::new(top), flags, RegionKind::Stack);
+    // Los mapeos de `mmap` van debajo, dejando una página de guarda
+    space.set_mmap_top(VirtAddr::new(bottom - 4096));
+    Ok(top)
+}
+
+/// Construye en la pila de `space` lo que espera el `_start` de un programa
+/// de Linux y devuelve el RSP inicial, alineado a 16 y apuntando a argc:
+///
+/// ```text
+/// argc | argv[0..] | NULL | envp[0..] | NULL | auxv (tipo, valor)... | AT_NULL | cadenas | 16 bytes aleatorios
+/// ```
+///
+/// Al `auxv` de quien llama se añade `AT_RANDOM`, que apunta a los bytes
+/// aleatorios (la libc los usa para el canario de la pila). Las palabras son
+/// de 4 bytes para los programas ELFCLASS32.
+pub fn build_initial_stack(
+    space: &mut AddressSpace,
+    stack_top: u64,
+    class: Class,
+    argv: &[String],
+    envp: &[String],
+    auxv: &[(u64, u64)],
+) -> Result<u64, ElfError> {
+    let bottom = stack_top - USER_STACK_PAGES * 4096;
+
+    let mut sp = stack_top - 16;
+    space.write(VirtAddr::new(sp), &random_bytes())?;
+    let random = sp;
+
+    // Después las cadenas, cada una terminada en NUL
+    let mut push_str = |space: &mut AddressSpace, s: &str| -> Result<u64, ElfError> {
+        sp = sp.checked_sub(s.len() as u64 + 1)
+            .filter(|&sp| sp >= bottom)
+            .ok_or(ElfError::ArgumentsTooLarge)?;
+        space.write(VirtAddr::new(sp), s.as_bytes())?;
+        space.zero(VirtAddr::new(sp + s.len() as u64), 1)?;
+        Ok(sp)
+    };
+    let envp_ptrs = envp.iter().map(|s| push_str(space, s)).collect::<Result<Vec<_>, _>>()?;
+    let argv_ptrs = argv.iter().map(|s| push_str(space, s)).collect::<Result<Vec<_>, _>>()?;
+
+    let mut words = Vec::new();
+    words.push(argv.len() as u64);
+    words.extend_from_slice(&argv_ptrs);
+    words.push(0);
+    words.extend_from_slice(&envp_ptrs);
+    words.push(0);
+    for &(kind, value) in auxv {
+        words.extend_from_slice(&[kind, value]);
+    }
+    words.extend_from_slice(&[crate::elf::AT_RANDOM, random]);
+    words.extend_from_slice(&[crate::elf::AT_NULL, 0]);
+
+    let bytes: Vec<u8> = match class {
+        Class::Elf64 => words.iter().flat_map(|word| word.to_ne_bytes()).collect(),
+        Class::Elf32 => words.iter().flat_map(|&word| (word as u32).to_ne_bytes()).collect(),
+    };
+    let rsp = sp.checked_sub(bytes.len() as u64)
+        .map(|rsp| rsp & !0xF)
+        .filter(|&rsp| rsp >= bottom)
+        .ok_or(ElfError::ArgumentsTooLarge)?;
+    space.write(VirtAddr::new(rsp), &bytes)?;
+    Ok(rsp)
+}
+
+/// 16 bytes para `AT_RANDOM`: de RDRAND si la CPU lo tiene y, si no, del
+/// contador de ciclos (no son criptográficos, pero cambian en cada arranque)
+fn random_bytes() -> [u8; 16] {
+    let rdrand = RdRand::new();
+    let mut bytes = [0u8; 16];
+    for (i, chunk) in bytes.chunks_mut(8).enumerate() {
+        let value = rdrand.and_then(RdRand::get_u64).unwrap_or_else(|| {
+            let tsc = unsafe { core::arch::x86_64::_rdtsc() };
+            // Mezclar para que los dos trozos no se parezcan
+            (tsc ^ ((i as u64) << 32)).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        });
+        chunk.copy_from_slice(&value.to_ne_bytes());
+    }
+    bytes
+}
+
+/// Salta a `entry` en ring 3 con `iretq`, en modo compatibilidad (32 bits)
+/// si `compat`.
+///
+/// # Safety
+/// `entry` y `stack_top` deben estar mapeados con `USER_ACCESSIBLE`.
+pub unsafe fn enter_user_mode(entry: u64, stack_top: u64, compat: bool) -> ! {
+    let selectors = crate::gdt::selectors();
+    let code = if compat { selectors.user_code32 } else { selectors.user_code };
+    asm!(
+        "push {ss}",
+        "push {rsp}",
+        "push {rflags}",
+        "push {cs}",
+        "push {rip}",
+        "iretq",
+        ss = in(reg) selectors.user_data.0 as u64,
+        rsp = in(reg) stack_top,
+        rflags = in(reg) USER_RFLAGS,
+        cs = in(reg) code.0 as u64,
+        rip = in(reg) entry,
+        options(noreturn)
+    )
+}
+
+/// Vuelve a ring 3 con todos los registros de `ctx` (para el hijo de `fork`)
+///
+/// # Safety
+/// `ctx` tiene que venir de una syscall del espacio de direcciones activo.
+pub unsafe fn return_to_user(ctx: &SyscallContext) -> ! {
+    let selectors = crate::gdt::selectors();
+    asm!(
+        "push {ss}",
+        "push qword ptr [rdi + 15*8]", // rsp
+        "push qword ptr [rdi + 14*8]", // rflags
+        "push {cs}",
+        "push qword ptr [rdi + 13*8]", // rip
+        "mov rax, [rdi]",
+        "mov rsi, [rdi + 2*8]",
+        "mov rdx, [rdi + 3*8]",
+        "mov r10, [rdi + 4*8]",
+        "mov r8, [rdi + 5*8]",
+        "mov r9, [rdi + 6*8]",
+        "mov rbx, [rdi + 7*8]",
+        "mov rbp, [rdi + 8*8]",
+        "mov r12, [rdi + 9*8]",
+        "mov r13, [rdi + 10*8]",
+        "mov r14, [rdi + 11*8]",
+        "mov r15, [rdi + 12*8]",
+        "mov rdi, [rdi + 8]",
+        "iretq",
+        in("rdi") ctx as *const SyscallContext,
+        ss = in(reg) selectors.user_data.0 as u64,
+        cs = in(reg) selectors.user_code.0 as u64,
+        options(noreturn)
+    )
+}
+
+/// Termina el programa actual con `signal` por un error suyo (no vuelve)
+pub fn kill_current(signal: u8, reason: core::fmt::Arguments) -> ! {
+    crate::println!("💀 Programa terminado: {}", reason);
+    crate::process::kill(signal)
+}